@@ -4,9 +4,14 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema_with_title, remove_schemas, schema_for};
 
 use eris_staking::hub::{
-    Batch, ConfigResponse, ExecuteMsg, FeeConfig, InstantiateMsg, PendingBatch, QueryMsg,
-    ReceiveMsg, StateResponse, UnbondRequestsByBatchResponseItem, UnbondRequestsByUserResponseItem,
-    UnbondRequestsByUserResponseItemDetails,
+    AprResponse, Batch, ConfigResponse, ContractInfoResponse, Cw721ReceiveMsg,
+    DelegationResponseItem, ExchangeRateSnapshot, ExecuteMsg, FeeConfig, InstantiateMsg,
+    NftInfoResponse, NumTokensResponse, OwnerOfResponse, PendingBatch, PendingChange, QueryMsg,
+    ReceiveMsg, RedelegationResponseItem, RouterExecuteMsg, RouterQueryMsg, SimulateBondResponse,
+    SimulateSwapOperationsResponse, SimulateUnbondResponse, StateResponse, StrategyQueryMsg,
+    SwapRoute, TokensResponse, UnbondRequestsByBatchResponseItem, UnbondRequestsByUserResponseItem,
+    UnbondRequestsByUserResponseItemDetails, UserVotes, ValidatorVotesResponse,
+    ValidatorWeightsResponse, WithdrawableResponse,
 };
 
 fn main() {
@@ -39,4 +44,50 @@ fn main() {
         "UnbondRequestsByUserResponseItemDetails",
     );
     export_schema_with_title(&schema_for!(ReceiveMsg), &out_dir, "ReceiveMsg");
+    export_schema_with_title(&schema_for!(StrategyQueryMsg), &out_dir, "StrategyQueryMsg");
+    export_schema_with_title(
+        &schema_for!(ValidatorWeightsResponse),
+        &out_dir,
+        "ValidatorWeightsResponse",
+    );
+    export_schema_with_title(
+        &schema_for!(RedelegationResponseItem),
+        &out_dir,
+        "RedelegationResponseItem",
+    );
+    export_schema_with_title(&schema_for!(UserVotes), &out_dir, "UserVotes");
+    export_schema_with_title(
+        &schema_for!(ValidatorVotesResponse),
+        &out_dir,
+        "ValidatorVotesResponse",
+    );
+    export_schema_with_title(&schema_for!(WithdrawableResponse), &out_dir, "WithdrawableResponse");
+    export_schema_with_title(&schema_for!(OwnerOfResponse), &out_dir, "OwnerOfResponse");
+    export_schema_with_title(&schema_for!(TokensResponse), &out_dir, "TokensResponse");
+    export_schema_with_title(&schema_for!(NftInfoResponse), &out_dir, "NftInfoResponse");
+    export_schema_with_title(&schema_for!(ContractInfoResponse), &out_dir, "ContractInfoResponse");
+    export_schema_with_title(&schema_for!(NumTokensResponse), &out_dir, "NumTokensResponse");
+    export_schema_with_title(&schema_for!(Cw721ReceiveMsg), &out_dir, "Cw721ReceiveMsg");
+    export_schema_with_title(&schema_for!(SwapRoute), &out_dir, "SwapRoute");
+    export_schema_with_title(&schema_for!(RouterExecuteMsg), &out_dir, "RouterExecuteMsg");
+    export_schema_with_title(&schema_for!(RouterQueryMsg), &out_dir, "RouterQueryMsg");
+    export_schema_with_title(
+        &schema_for!(SimulateSwapOperationsResponse),
+        &out_dir,
+        "SimulateSwapOperationsResponse",
+    );
+    export_schema_with_title(&schema_for!(PendingChange), &out_dir, "PendingChange");
+    export_schema_with_title(&schema_for!(ExchangeRateSnapshot), &out_dir, "ExchangeRateSnapshot");
+    export_schema_with_title(&schema_for!(AprResponse), &out_dir, "AprResponse");
+    export_schema_with_title(&schema_for!(SimulateBondResponse), &out_dir, "SimulateBondResponse");
+    export_schema_with_title(
+        &schema_for!(SimulateUnbondResponse),
+        &out_dir,
+        "SimulateUnbondResponse",
+    );
+    export_schema_with_title(
+        &schema_for!(DelegationResponseItem),
+        &out_dir,
+        "DelegationResponseItem",
+    );
 }
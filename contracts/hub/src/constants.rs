@@ -7,7 +7,39 @@ pub const CONTRACT_DENOM: &str = "ujuno";
 #[cfg(feature = "testnet")]
 pub const CONTRACT_DENOM: &str = "ujunox";
 
+/// Target weight of validators that have not been assigned one explicitly
+pub const DEFAULT_VALIDATOR_WEIGHT: u64 = 100;
+
+/// Number of unbond requests paid out by a call to `distribute_unbonded`, if not specified
+pub const DEFAULT_DISTRIBUTION_LIMIT: u32 = 10;
+/// Maximum number of unbond requests paid out by a call to `distribute_unbonded`
+pub const MAX_DISTRIBUTION_LIMIT: u32 = 30;
+
+/// Name and symbol of the cw721 tokens representing unbond requests
+pub const UNBOND_NFT_NAME: &str = "Eris unbond requests";
+pub const UNBOND_NFT_SYMBOL: &str = "ERIS-UNBOND";
+
+/// Number of exchange rate snapshots kept; the oldest is dropped when a new one is recorded
+pub const EXCHANGE_RATE_HISTORY_SIZE: u64 = 100;
+
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
 pub fn get_reward_fee_cap() -> Decimal {
     // 10% max reward fee
     Decimal::from_ratio(10_u128, 100_u128)
 }
+
+pub fn get_deposit_fee_cap() -> Decimal {
+    // 1% max deposit fee
+    Decimal::from_ratio(1_u128, 100_u128)
+}
+
+pub fn get_withdrawal_fee_cap() -> Decimal {
+    // 1% max withdrawal fee
+    Decimal::from_ratio(1_u128, 100_u128)
+}
+
+pub fn get_instant_unbond_fee_cap() -> Decimal {
+    // 5% max instant unbond fee
+    Decimal::from_ratio(5_u128, 100_u128)
+}
@@ -1,19 +1,22 @@
 use cosmwasm_std::{
-    entry_point, from_binary, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response,
-    StdError, StdResult,
+    entry_point, from_binary, to_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo,
+    Order, Reply, Response, StdResult,
 };
 use cw2::{get_contract_version, set_contract_version};
 use cw20::Cw20ReceiveMsg;
+use serde::{Deserialize, Serialize};
 
 use cw_storage_plus::Item;
 use eris_staking::hub::{
-    CallbackMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ReceiveMsg,
+    CallbackMsg, ConfigChange, DepositFeeSource, ExecuteMsg, FeeConfig, InstantiateMsg, MigrateMsg,
+    QueryMsg, ReceiveMsg,
 };
 
 use crate::constants::{CONTRACT_DENOM, CONTRACT_NAME, CONTRACT_VERSION};
+use crate::error::{ContractError, ContractResult};
 use crate::helpers::{parse_received_fund, unwrap_reply};
 use crate::state::State;
-use crate::{execute, queries};
+use crate::{execute, gauges, queries, timelock, unbond_nft};
 
 #[entry_point]
 pub fn instantiate(
@@ -21,12 +24,19 @@ pub fn instantiate(
     env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> ContractResult<Response> {
     execute::instantiate(deps, env, msg)
 }
 
 #[entry_point]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> ContractResult<Response> {
+    assert_not_paused(deps.as_ref(), &msg)?;
+
     let api = deps.api;
     match msg {
         ExecuteMsg::Receive(cw20_msg) => receive(deps, env, info, cw20_msg),
@@ -48,61 +58,226 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
         ),
         ExecuteMsg::WithdrawUnbonded {
             receiver,
+            batch_ids,
+            limit,
         } => execute::withdraw_unbonded(
             deps,
             env,
             info.sender.clone(),
             receiver.map(|s| api.addr_validate(&s)).transpose()?.unwrap_or(info.sender),
+            batch_ids,
+            limit,
         ),
+        ExecuteMsg::CancelUnbond {
+            amount,
+        } => execute::cancel_unbond(deps, env, info.sender, amount),
+        ExecuteMsg::TransferUnbondRequest {
+            id,
+            recipient,
+        } => execute::transfer_unbond_request(deps, env, info.sender, id, recipient),
+        ExecuteMsg::TransferNft {
+            recipient,
+            token_id,
+        } => unbond_nft::transfer_nft(deps, env, info.sender, recipient, token_id),
+        ExecuteMsg::SendNft {
+            contract,
+            token_id,
+            msg,
+        } => unbond_nft::send_nft(deps, env, info.sender, contract, token_id, msg),
+        ExecuteMsg::Approve {
+            spender,
+            token_id,
+            expires,
+        } => unbond_nft::approve(deps, env, info.sender, spender, token_id, expires),
+        ExecuteMsg::Revoke {
+            spender,
+            token_id,
+        } => unbond_nft::revoke(deps, env, info.sender, spender, token_id),
+        ExecuteMsg::ApproveAll {
+            operator,
+            expires,
+        } => unbond_nft::approve_all(deps, env, info.sender, operator, expires),
+        ExecuteMsg::RevokeAll {
+            operator,
+        } => unbond_nft::revoke_all(deps, info.sender, operator),
         ExecuteMsg::AddValidator {
             validator,
-        } => execute::add_validator(deps, info.sender, validator),
+            weight,
+        } => timelock::propose_change(
+            deps,
+            env,
+            info.sender,
+            ConfigChange::AddValidator {
+                validator,
+                weight,
+            },
+        ),
         ExecuteMsg::RemoveValidator {
             validator,
-        } => execute::remove_validator(deps, env, info.sender, validator),
+        } => timelock::propose_change(
+            deps,
+            env,
+            info.sender,
+            ConfigChange::RemoveValidator {
+                validator,
+            },
+        ),
+        ExecuteMsg::CheckValidators {} => execute::check_validators(deps, env),
+        ExecuteMsg::UpdateValidatorWeights {
+            weights,
+        } => timelock::propose_change(
+            deps,
+            env,
+            info.sender,
+            ConfigChange::UpdateValidatorWeights {
+                weights,
+            },
+        ),
+        ExecuteMsg::UpdateLiquidityBuffer {
+            config,
+        } => timelock::propose_change(
+            deps,
+            env,
+            info.sender,
+            ConfigChange::UpdateLiquidityBuffer {
+                config,
+            },
+        ),
+        ExecuteMsg::UpdateSwapRoutes {
+            add,
+            remove,
+        } => execute::update_swap_routes(deps, info.sender, add, remove),
+        ExecuteMsg::GrantRole {
+            role,
+            account,
+        } => execute::grant_role(deps, info.sender, role, account),
+        ExecuteMsg::RevokeRole {
+            role,
+            account,
+        } => execute::revoke_role(deps, info.sender, role, account),
         ExecuteMsg::TransferOwnership {
             new_owner,
         } => execute::transfer_ownership(deps, info.sender, new_owner),
         ExecuteMsg::AcceptOwnership {} => execute::accept_ownership(deps, info.sender),
+        ExecuteMsg::Pause(flags) => execute::pause(deps, info.sender, flags),
+        ExecuteMsg::Unpause(flags) => execute::unpause(deps, info.sender, flags),
         ExecuteMsg::Harvest {} => execute::harvest(deps, env),
-        ExecuteMsg::Rebalance {} => execute::rebalance(deps, env),
+        ExecuteMsg::Rebalance {
+            min_deviation,
+            max_redelegations,
+            force,
+        } => execute::rebalance(deps, env, info.sender, min_deviation, max_redelegations, force),
         ExecuteMsg::Reconcile {} => execute::reconcile(deps, env),
-        ExecuteMsg::SubmitBatch {} => execute::submit_batch(deps, env),
+        ExecuteMsg::DistributeUnbonded {
+            limit,
+        } => execute::distribute_unbonded(deps, env, limit),
+        ExecuteMsg::SubmitBatch {
+            force,
+        } => execute::submit_batch(deps, env, info.sender, force),
         ExecuteMsg::Callback(callback_msg) => callback(deps, env, info, callback_msg),
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract,
-            protocol_reward_fee,
-        } => execute::update_config(deps, info.sender, protocol_fee_contract, protocol_reward_fee),
+        ExecuteMsg::Vote {
+            votes,
+        } => gauges::vote(deps, info.sender, votes),
+        ExecuteMsg::UnlockVotingPower {
+            amount,
+        } => gauges::unlock_voting_power(deps, info.sender, amount),
+        ExecuteMsg::TallyVotes {} => gauges::tally_votes(deps, env),
+        ExecuteMsg::UpdateConfig(msg) => {
+            timelock::propose_change(deps, env, info.sender, ConfigChange::UpdateConfig(msg))
+        },
+        ExecuteMsg::UpdateTimelockDelay {
+            delay,
+        } => timelock::propose_change(
+            deps,
+            env,
+            info.sender,
+            ConfigChange::UpdateTimelockDelay {
+                delay,
+            },
+        ),
+        ExecuteMsg::ExecutePendingChange {
+            id,
+        } => timelock::execute_pending_change(deps, env, info.sender, id),
+        ExecuteMsg::CancelPendingChange {
+            id,
+        } => timelock::cancel_pending_change(deps, info.sender, id),
     }
 }
 
+fn assert_not_paused(deps: Deps, msg: &ExecuteMsg) -> ContractResult<()> {
+    let paused = State::default().pause_state.may_load(deps.storage)?.unwrap_or_default();
+
+    // Locking voting power is a receive hook too, but it isn't an unbonding
+    let unbonding_hook = match msg {
+        ExecuteMsg::Receive(cw20_msg) => matches!(
+            from_binary(&cw20_msg.msg),
+            Ok(ReceiveMsg::QueueUnbond { .. } | ReceiveMsg::InstantUnbond { .. })
+        ),
+        _ => false,
+    };
+
+    let operation = match msg {
+        ExecuteMsg::Bond {
+            ..
+        }
+        | ExecuteMsg::Donate {}
+            if paused.bond =>
+        {
+            "bonding"
+        },
+        ExecuteMsg::Receive(_) if unbonding_hook && paused.unbond => "unbonding",
+        ExecuteMsg::SubmitBatch {
+            ..
+        } if paused.unbond => "unbonding",
+        ExecuteMsg::WithdrawUnbonded {
+            ..
+        }
+        | ExecuteMsg::DistributeUnbonded {
+            ..
+        } if paused.withdraw => "withdrawing",
+        ExecuteMsg::Harvest {} if paused.harvest => "harvesting",
+        _ => return Ok(()),
+    };
+
+    Err(ContractError::Paused(operation.to_string()))
+}
+
 fn receive(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     cw20_msg: Cw20ReceiveMsg,
-) -> StdResult<Response> {
+) -> ContractResult<Response> {
     let api = deps.api;
+
+    let state = State::default();
+    let stake_token = state.stake_token.load(deps.storage)?;
+    if info.sender != stake_token {
+        return Err(ContractError::InvalidStakeToken(info.sender.into()));
+    }
+
     match from_binary(&cw20_msg.msg)? {
         ReceiveMsg::QueueUnbond {
             receiver,
-        } => {
-            let state = State::default();
-
-            let stake_token = state.stake_token.load(deps.storage)?;
-            if info.sender != stake_token {
-                return Err(StdError::generic_err(format!(
-                    "expecting Stake token, received {}",
-                    info.sender
-                )));
-            }
-
-            execute::queue_unbond(
-                deps,
-                env,
-                api.addr_validate(&receiver.unwrap_or(cw20_msg.sender))?,
-                cw20_msg.amount,
-            )
+            auto_claim,
+        } => execute::queue_unbond(
+            deps,
+            env,
+            api.addr_validate(&cw20_msg.sender)?,
+            api.addr_validate(&receiver.unwrap_or_else(|| cw20_msg.sender.clone()))?,
+            cw20_msg.amount,
+            auto_claim,
+        ),
+        ReceiveMsg::InstantUnbond {
+            receiver,
+        } => execute::instant_unbond(
+            deps,
+            env,
+            api.addr_validate(&receiver.unwrap_or(cw20_msg.sender))?,
+            cw20_msg.amount,
+        ),
+        ReceiveMsg::LockVotingPower {} => {
+            gauges::lock_voting_power(deps, api.addr_validate(&cw20_msg.sender)?, cw20_msg.amount)
         },
     }
 }
@@ -112,12 +287,13 @@ fn callback(
     env: Env,
     info: MessageInfo,
     callback_msg: CallbackMsg,
-) -> StdResult<Response> {
+) -> ContractResult<Response> {
     if env.contract.address != info.sender {
-        return Err(StdError::generic_err("callbacks can only be invoked by the contract itself"));
+        return Err(ContractError::UnauthorizedCallback);
     }
 
     match callback_msg {
+        CallbackMsg::Swap {} => execute::swap(deps, env),
         CallbackMsg::Reinvest {} => execute::reinvest(deps, env),
         CallbackMsg::CheckReceivedCoin {
             snapshot,
@@ -126,10 +302,11 @@ fn callback(
 }
 
 #[entry_point]
-pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> StdResult<Response> {
+pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> ContractResult<Response> {
     match reply.id {
         1 => execute::register_stake_token(deps, unwrap_reply(reply)?),
-        id => Err(StdError::generic_err(format!("invalid reply id: {}; must be 1", id))),
+        2 => execute::skip_failed_swap(reply),
+        id => Err(ContractError::InvalidReplyId(id)),
     }
 }
 
@@ -166,11 +343,67 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             limit,
             env,
         )?),
+        QueryMsg::UserVotes {
+            user,
+        } => to_binary(&gauges::user_votes(deps, user)?),
+        QueryMsg::ValidatorVotes {} => to_binary(&gauges::validator_votes(deps)?),
+        QueryMsg::SimulateRebalance {
+            min_deviation,
+            max_redelegations,
+            force,
+        } => to_binary(&queries::simulate_rebalance(
+            deps,
+            env,
+            min_deviation,
+            max_redelegations,
+            force,
+        )?),
+        QueryMsg::RoleHolders {
+            role,
+            start_after,
+            limit,
+        } => to_binary(&queries::role_holders(deps, role, start_after, limit)?),
+        QueryMsg::PendingChanges {
+            start_after,
+            limit,
+        } => to_binary(&timelock::pending_changes(deps, start_after, limit)?),
+        QueryMsg::SwapRoutes {} => to_binary(&queries::swap_routes(deps)?),
+        QueryMsg::Withdrawable {
+            user,
+        } => to_binary(&queries::withdrawable(deps, env, user)?),
+        QueryMsg::SimulateBond {
+            amount,
+        } => to_binary(&queries::simulate_bond(deps, env, amount)?),
+        QueryMsg::SimulateUnbond {
+            ustake,
+        } => to_binary(&queries::simulate_unbond(deps, env, ustake)?),
+        QueryMsg::Delegations {} => to_binary(&queries::delegations(deps, env)?),
+        QueryMsg::ExchangeRates {
+            start_after,
+            limit,
+        } => to_binary(&queries::exchange_rates(deps, start_after, limit)?),
+        QueryMsg::Apr {
+            window,
+        } => to_binary(&queries::apr(deps, window)?),
+        QueryMsg::OwnerOf {
+            token_id,
+            include_expired,
+        } => to_binary(&unbond_nft::owner_of(deps, env, token_id, include_expired)?),
+        QueryMsg::NftInfo {
+            token_id,
+        } => to_binary(&unbond_nft::nft_info(deps, token_id)?),
+        QueryMsg::ContractInfo {} => to_binary(&unbond_nft::contract_info()),
+        QueryMsg::NumTokens {} => to_binary(&unbond_nft::num_tokens(deps)?),
+        QueryMsg::Tokens {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&unbond_nft::tokens(deps, owner, start_after, limit)?),
     }
 }
 
 #[entry_point]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> ContractResult<Response> {
     let contract_version = get_contract_version(deps.storage)?;
     //
     match contract_version.contract.as_ref() {
@@ -183,14 +416,35 @@ pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response
                     item.remove(deps.storage)
                 }
             }
-        },
 
-        _ => {
-            return Err(StdError::generic_err(format!(
-                "wrong contract name {}",
-                contract_version.contract
-            )))
+            // Unbond requests queued before they were counted as tokens
+            let state = State::default();
+            if state.unbond_request_count.may_load(deps.storage)?.is_none() {
+                let count =
+                    state.unbond_requests.keys(deps.storage, None, None, Order::Ascending).count();
+                state.unbond_request_count.save(deps.storage, &(count as u64))?;
+            }
+
+            // Fee configs saved before protocol fees could be split have a single recipient
+            let legacy_fee_config: Item<LegacyFeeConfig> = Item::new("fee_config");
+            if let Ok(legacy) = legacy_fee_config.load(deps.storage) {
+                State::default().fee_config.save(
+                    deps.storage,
+                    &FeeConfig {
+                        protocol_fee_recipients: vec![(
+                            legacy.protocol_fee_contract,
+                            Decimal::one(),
+                        )],
+                        protocol_reward_fee: legacy.protocol_reward_fee,
+                        deposit_fee: Decimal::zero(),
+                        deposit_fee_source: DepositFeeSource::Deposit,
+                        withdrawal_fee: Decimal::zero(),
+                    },
+                )?;
+            }
         },
+
+        _ => return Err(ContractError::WrongContractName(contract_version.contract)),
     }
 
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
@@ -201,3 +455,9 @@ pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response
         .add_attribute("new_contract_name", CONTRACT_NAME)
         .add_attribute("new_contract_version", CONTRACT_VERSION))
 }
+
+#[derive(Deserialize, Serialize)]
+struct LegacyFeeConfig {
+    protocol_fee_contract: Addr,
+    protocol_reward_fee: Decimal,
+}
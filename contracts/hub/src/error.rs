@@ -0,0 +1,240 @@
+use cosmwasm_std::{Decimal, OverflowError, StdError, Uint128};
+use thiserror::Error;
+
+use eris_staking::hub::Role;
+
+/// Errors returned by the hub's entry points. Integrators should match on the variants rather than
+/// on the error messages, which may change
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    //----------------------------------------------------------------------------------------------
+    // Authorization
+    //----------------------------------------------------------------------------------------------
+    #[error("unauthorized: sender is not owner")]
+    Unauthorized,
+
+    #[error("unauthorized: sender is not owner or {}", .0.as_str())]
+    UnauthorizedRole(Role),
+
+    #[error("unauthorized: sender is not new owner")]
+    UnauthorizedNewOwner,
+
+    #[error("callbacks can only be invoked by the contract itself")]
+    UnauthorizedCallback,
+
+    #[error("expecting Stake token, received {0}")]
+    InvalidStakeToken(String),
+
+    #[error("{account} does not hold the {} role", .role.as_str())]
+    RoleNotHeld {
+        account: String,
+        role: Role,
+    },
+
+    #[error("{0} is paused")]
+    Paused(String),
+
+    //----------------------------------------------------------------------------------------------
+    // Deposits and replies
+    //----------------------------------------------------------------------------------------------
+    #[error("must deposit exactly one coin; received {0}")]
+    InvalidFundsCount(usize),
+
+    #[error("expected {expected} deposit, received {received}")]
+    InvalidDenom {
+        expected: String,
+        received: String,
+    },
+
+    #[error("deposit amount must be non-zero")]
+    ZeroDeposit,
+
+    #[error("failed to parse coin: {0}")]
+    InvalidCoin(String),
+
+    #[error("Address {0} should be lowercase")]
+    NonLowercaseAddress(String),
+
+    #[error("{0}")]
+    SubMsgFailed(String),
+
+    #[error("invalid reply id: {0}; must be 1 or 2")]
+    InvalidReplyId(u64),
+
+    #[error("cannot find `instantiate` event")]
+    InstantiateEventNotFound,
+
+    #[error("cannot find `_contract_address` attribute")]
+    ContractAddressNotFound,
+
+    #[error("no {0} available to be bonded")]
+    NothingToReinvest(String),
+
+    #[error("wrong contract name {0}")]
+    WrongContractName(String),
+
+    //----------------------------------------------------------------------------------------------
+    // Unbonding
+    //----------------------------------------------------------------------------------------------
+    #[error("no unbond request from {user} in batch {id}")]
+    UnbondRequestNotFound {
+        id: u64,
+        user: String,
+    },
+
+    #[error("can not cancel {requested} ustake, the unbond request holds {available}")]
+    CancelAmountTooHigh {
+        requested: Uint128,
+        available: Uint128,
+    },
+
+    #[error("can not transfer an unbond request to its owner")]
+    TransferToOwner,
+
+    #[error("unbond request {0} is held by another account")]
+    UnbondRequestHeldByAnother(String),
+
+    #[error("liquidity buffer holds {available} utoken, instant unbonding requires {required}")]
+    InsufficientBuffer {
+        available: Uint128,
+        required: Uint128,
+    },
+
+    #[error("can not unbond {ustake} ustake, the total supply is {ustake_supply}")]
+    ExceedsTotalSupply {
+        ustake: Uint128,
+        ustake_supply: Uint128,
+    },
+
+    #[error("batch can only be submitted for unbonding after {0}")]
+    BatchNotReady(u64),
+
+    #[error("withdrawable amount is zero")]
+    NothingToWithdraw,
+
+    #[error("no unbond requests to distribute")]
+    NothingToDistribute,
+
+    #[error("invalid token id {0}")]
+    InvalidTokenId(String),
+
+    #[error("unbond requests can only be transferred once their batch is submitted")]
+    UnbondRequestNotTransferable,
+
+    #[error("unauthorized: sender can not transfer unbond request {0}")]
+    NotTokenHolder(String),
+
+    #[error("approval expiration has already passed")]
+    ApprovalExpired,
+
+    //----------------------------------------------------------------------------------------------
+    // Validators
+    //----------------------------------------------------------------------------------------------
+    #[error("validator {0} is already whitelisted")]
+    ValidatorAlreadyWhitelisted(String),
+
+    #[error("validator {0} is not whitelisted")]
+    ValidatorNotWhitelisted(String),
+
+    #[error("validator {0} is not in the active set")]
+    ValidatorNotActive(String),
+
+    #[error("validator commission {commission} exceeds the maximum {max_commission}")]
+    CommissionTooHigh {
+        commission: Decimal,
+        max_commission: Decimal,
+    },
+
+    #[error("no validator needs to be removed")]
+    NoValidatorToRemove,
+
+    #[error("cannot remove all whitelisted validators")]
+    CannotRemoveAllValidators,
+
+    #[error("validator {validator} can not be redelegated from until {completion_time}")]
+    RedelegationBlocked {
+        validator: String,
+        completion_time: u64,
+    },
+
+    #[error(
+        "validators {0} can not be redelegated from until their incoming redelegations complete"
+    )]
+    ValidatorsBlocked(String),
+
+    #[error("duplicate vote for validator {0}")]
+    DuplicateVote(String),
+
+    #[error("votes must add up to at most {max_bps} bps, received {total_bps}")]
+    VotesExceedMax {
+        max_bps: u16,
+        total_bps: u32,
+    },
+
+    #[error("votes can only be tallied after {0}")]
+    TallyNotReady(u64),
+
+    #[error("can not unlock {requested} ustake, {locked} ustake are locked")]
+    UnlockAmountTooHigh {
+        requested: Uint128,
+        locked: Uint128,
+    },
+
+    //----------------------------------------------------------------------------------------------
+    // Configuration
+    //----------------------------------------------------------------------------------------------
+    #[error("'{0}' greater than max")]
+    GreaterThanMax(String),
+
+    #[error("'{0}' greater than 1")]
+    GreaterThanOne(String),
+
+    #[error("liquidity buffer shares can not be greater than 1")]
+    InvalidBufferShares,
+
+    #[error("can not swap {0}")]
+    CannotSwapContractDenom(String),
+
+    #[error("invalid swap route for {0}")]
+    InvalidSwapRoute(String),
+
+    #[error("minimum price of the swap route for {0} must be greater than 0")]
+    ZeroSwapMinPrice(String),
+
+    #[error("protocol fee share of {0} must be greater than 0")]
+    ZeroProtocolFeeShare(String),
+
+    #[error("duplicate protocol fee recipient {0}")]
+    DuplicateProtocolFeeRecipient(String),
+
+    #[error("protocol fee shares must sum to 1")]
+    InvalidProtocolFeeShares,
+
+    #[error("pending change {0} not found")]
+    PendingChangeNotFound(u64),
+
+    #[error("pending change {id} can only be executed after {executable_at}")]
+    PendingChangeNotReady {
+        id: u64,
+        executable_at: u64,
+    },
+}
+
+pub type ContractResult<T> = Result<T, ContractError>;
+
+/// Queries share some logic with the entry points, so contract errors can be returned as standard
+/// errors, keeping the message
+impl From<ContractError> for StdError {
+    fn from(err: ContractError) -> Self {
+        match err {
+            ContractError::Std(err) => err,
+            err => StdError::generic_err(err.to_string()),
+        }
+    }
+}
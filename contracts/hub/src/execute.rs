@@ -1,6 +1,7 @@
 use cosmwasm_std::{
-    to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, DepsMut, DistributionMsg, Env, Event,
-    Order, Response, StdError, StdResult, SubMsg, SubMsgResponse, Uint128, WasmMsg,
+    to_binary, Addr, Api, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, DistributionMsg, Empty,
+    Env, Event, Order, Reply, Response, StdResult, Storage, SubMsg, SubMsgResponse, Uint128,
+    WasmMsg,
 };
 use cw2::set_contract_version;
 use cw20::{Cw20ExecuteMsg, MinterResponse};
@@ -8,17 +9,32 @@ use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
 use eris_staking::{CustomResponse, DecimalCheckedOps};
 
 use eris_staking::hub::{
-    Batch, CallbackMsg, ExecuteMsg, FeeConfig, InstantiateMsg, PendingBatch, UnbondRequest,
+    Batch, CallbackMsg, DelegationStrategyConfig, DepositFeeSource, ExchangeRateSnapshot,
+    ExecuteMsg, FeeConfig, InstantiateMsg, LiquidityBufferConfig, PauseState, PendingBatch, Role,
+    RouterExecuteMsg, RouterQueryMsg, SimulateSwapOperationsResponse, SwapRoute, UpdateConfigMsg,
 };
 
-use crate::constants::{get_reward_fee_cap, CONTRACT_DENOM};
-use crate::helpers::{dedupe, query_cw20_total_supply, query_delegation, query_delegations};
+use crate::constants::{
+    get_deposit_fee_cap, get_instant_unbond_fee_cap, get_reward_fee_cap, get_withdrawal_fee_cap,
+    CONTRACT_DENOM, DEFAULT_DISTRIBUTION_LIMIT, DEFAULT_VALIDATOR_WEIGHT, MAX_DISTRIBUTION_LIMIT,
+};
+use crate::error::{ContractError, ContractResult};
+use crate::gauges::remove_validator_votes;
+use crate::helpers::{
+    dedupe, query_active_validator, query_cw20_total_supply, query_delegation, query_delegations,
+};
 use crate::math::{
-    compute_mint_amount, compute_redelegations_for_rebalancing, compute_redelegations_for_removal,
-    compute_unbond_amount, compute_undelegations, mark_reconciled_batches, reconcile_batches,
+    compute_bond_amounts, compute_buffer_refill, compute_exchange_rate, compute_unbond_amount,
+    deduct_withdrawal_fee, mark_reconciled_batches, reconcile_batches, split_protocol_fee,
+    withdraw_from_batch, BondAmounts,
 };
 use crate::state::State;
-use crate::types::{Coins, Delegation, SendFee};
+use crate::strategy::{load_strategy, plan_rebalancing, validate_strategy_config};
+use crate::types::{Coins, Delegation, Redelegation, SendFee};
+use crate::unbond_nft::{
+    add_to_unbond_request, query_holder, remove_unbond_request, token_id,
+    withdrawable_unbond_requests,
+};
 
 const CONTRACT_NAME: &str = "eris-hub";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -27,13 +43,13 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 // Instantiation
 //--------------------------------------------------------------------------------------------------
 
-pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> StdResult<Response> {
+pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> ContractResult<Response> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     let state = State::default();
 
     if msg.protocol_reward_fee.gt(&get_reward_fee_cap()) {
-        return Err(StdError::generic_err("'protocol_reward_fee' greater than max"));
+        return Err(ContractError::GreaterThanMax("protocol_reward_fee".to_string()));
     }
 
     state.owner.save(deps.storage, &deps.api.addr_validate(&msg.owner)?)?;
@@ -44,12 +60,25 @@ pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> StdResult<Re
     dedupe(&mut validators);
 
     state.validators.save(deps.storage, &validators)?;
+    state.delegation_strategy.save(
+        deps.storage,
+        &validate_strategy_config(
+            deps.api,
+            msg.delegation_strategy.unwrap_or(DelegationStrategyConfig::Weighted),
+        )?,
+    )?;
     state.unlocked_coins.save(deps.storage, &vec![])?;
     state.fee_config.save(
         deps.storage,
         &FeeConfig {
-            protocol_fee_contract: deps.api.addr_validate(&msg.protocol_fee_contract)?,
+            protocol_fee_recipients: vec![(
+                deps.api.addr_validate(&msg.protocol_fee_contract)?,
+                Decimal::one(),
+            )],
             protocol_reward_fee: msg.protocol_reward_fee,
+            deposit_fee: Decimal::zero(),
+            deposit_fee_source: DepositFeeSource::Deposit,
+            withdrawal_fee: Decimal::zero(),
         },
     )?;
 
@@ -84,20 +113,20 @@ pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> StdResult<Re
     )))
 }
 
-pub fn register_stake_token(deps: DepsMut, response: SubMsgResponse) -> StdResult<Response> {
+pub fn register_stake_token(deps: DepsMut, response: SubMsgResponse) -> ContractResult<Response> {
     let state = State::default();
 
     let event = response
         .events
         .iter()
         .find(|event| event.ty == "instantiate")
-        .ok_or_else(|| StdError::generic_err("cannot find `instantiate` event"))?;
+        .ok_or(ContractError::InstantiateEventNotFound)?;
 
     let contract_addr_str = &event
         .attributes
         .iter()
         .find(|attr| attr.key == "_contract_address")
-        .ok_or_else(|| StdError::generic_err("cannot find `_contract_address` attribute"))?
+        .ok_or(ContractError::ContractAddressNotFound)?
         .value;
 
     let contract_addr = deps.api.addr_validate(contract_addr_str)?;
@@ -114,75 +143,121 @@ pub fn register_stake_token(deps: DepsMut, response: SubMsgResponse) -> StdResul
 /// they all have the same amount of delegation. This is however quite gas-expensive: $1.5 cost in
 /// the case of 15 validators.
 ///
-/// To save gas for users, now we simply delegate all deposited Token to the validator with the
-/// smallest amount of delegation. If delegations become severely unbalance as a result of this
-/// (e.g. when a single user makes a very big deposit), anyone can invoke `ExecuteMsg::Rebalance`
-/// to balance the delegations.
+/// To save gas for users, now we simply delegate all deposited Token to the validator picked by the
+/// delegation strategy, i.e. the one with the smallest amount of delegation relative to its target
+/// weight. If delegations become severely unbalance as a result of this (e.g. when a single user
+/// makes a very big deposit), anyone can invoke `ExecuteMsg::Rebalance` to balance the delegations.
 pub fn bond(
     deps: DepsMut,
     env: Env,
     receiver: Addr,
     token_to_bond: Uint128,
     donate: bool,
-) -> StdResult<Response> {
+) -> ContractResult<Response> {
     let state = State::default();
     let stake_token = state.stake_token.load(deps.storage)?;
     let validators = state.validators.load(deps.storage)?;
 
-    // Query the current delegations made to validators, and find the validator with the smallest
-    // delegated amount through a linear search
-    // The code for linear search is a bit uglier than using `sort_by` but cheaper: O(n) vs O(n * log(n))
+    // Query the current delegations made to validators, and let the delegation strategy pick the
+    // validator that should receive the deposit
     let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
-    let mut validator = &delegations[0].validator;
-    let mut amount = delegations[0].amount;
-    for d in &delegations[1..] {
-        if d.amount < amount {
-            validator = &d.validator;
-            amount = d.amount;
-        }
-    }
-    let new_delegation = Delegation {
-        validator: validator.clone(),
-        amount: token_to_bond.u128(),
-    };
+    let strategy = load_strategy(deps.as_ref())?;
+    let validator = &strategy.validator_to_delegate(deps.as_ref(), &delegations)?.validator;
 
-    // Query the current supply of Staking Token and compute the amount to mint
+    // Query the current supply of Staking Token and compute the amount to mint, and the deposit fee
+    let fee_config = state.fee_config.load(deps.storage)?;
     let ustake_supply = query_cw20_total_supply(&deps.querier, &stake_token)?;
-    let ustake_to_mint = if donate {
-        Uint128::zero()
+    let utoken_buffered = state.buffer.may_load(deps.storage)?.unwrap_or_default();
+    let BondAmounts {
+        utoken_deposit_fee,
+        utoken_to_stake: token_to_stake,
+        ustake_to_mint,
+        ustake_deposit_fee,
+        ustake_to_receiver,
+    } = compute_bond_amounts(
+        &fee_config,
+        token_to_bond,
+        donate,
+        ustake_supply,
+        &delegations,
+        utoken_buffered,
+    )?;
+
+    // While the liquidity buffer is below its target, part of the deposit is kept in it
+    let buffer_config = state.buffer_config.may_load(deps.storage)?.unwrap_or_default();
+    let utoken_to_buffer = compute_buffer_refill(
+        token_to_stake,
+        buffer_config.bond_share,
+        utoken_buffered,
+        buffer_config.target,
+    )?;
+    let utoken_to_delegate = token_to_stake - utoken_to_buffer;
+
+    let delegate_msg = if utoken_to_delegate.is_zero() {
+        None
     } else {
-        compute_mint_amount(ustake_supply, token_to_bond, &delegations)
+        Some(Delegation::new(validator, utoken_to_delegate.u128()).to_cosmos_msg())
     };
 
+    if !utoken_to_buffer.is_zero() {
+        state.buffer.save(deps.storage, &(utoken_buffered + utoken_to_buffer))?;
+    }
+
     let event = Event::new("erishub/bonded")
         .add_attribute("time", env.block.time.seconds().to_string())
         .add_attribute("height", env.block.height.to_string())
         .add_attribute("receiver", receiver.clone())
         .add_attribute("token_bonded", token_to_bond)
-        .add_attribute("ustake_minted", ustake_to_mint);
-
-    let mint_msg = if donate {
-        None
-    } else {
-        Some(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: stake_token.into(),
-            msg: to_binary(&Cw20ExecuteMsg::Mint {
-                recipient: receiver.to_string(),
-                amount: ustake_to_mint,
-            })?,
-            funds: vec![],
-        }))
-    };
+        .add_attribute("utoken_buffered", utoken_to_buffer)
+        .add_attribute("ustake_minted", ustake_to_mint)
+        .add_attribute("utoken_deposit_fee", utoken_deposit_fee)
+        .add_attribute("ustake_deposit_fee", ustake_deposit_fee);
+
+    let mut mint_msgs = vec![];
+    if !donate {
+        mint_msgs.push(mint_msg(&stake_token, receiver.to_string(), ustake_to_receiver)?);
+    }
+    for (recipient, amount) in
+        split_protocol_fee(ustake_deposit_fee, &fee_config.protocol_fee_recipients)
+    {
+        mint_msgs.push(mint_msg(&stake_token, recipient.into(), amount)?);
+    }
 
+    // The part of the deposit kept in the buffer stays in the contract's balance, so it must not be
+    // mistaken for received coins, and neither must the deposit fee sent out
     Ok(Response::new()
-        .add_message(new_delegation.to_cosmos_msg())
-        .add_optional_message(mint_msg)
-        .add_message(check_received_coin_msg(&deps, &env, Some(token_to_bond))?)
+        .add_optional_message(delegate_msg)
+        .add_messages(mint_msgs)
+        .add_messages(send_protocol_fee_msgs(&fee_config, utoken_deposit_fee))
+        .add_message(check_received_coin_msg(
+            &deps,
+            &env,
+            Some(utoken_to_delegate + utoken_deposit_fee),
+        )?)
         .add_event(event)
         .add_attribute("action", "erishub/bond"))
 }
 
-pub fn harvest(deps: DepsMut, env: Env) -> StdResult<Response> {
+fn mint_msg(stake_token: &Addr, recipient: String, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: stake_token.into(),
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient,
+            amount,
+        })?,
+        funds: vec![],
+    }))
+}
+
+/// Send a fee in Token to the protocol fee recipients, split according to their shares
+fn send_protocol_fee_msgs(fee_config: &FeeConfig, utoken_fee: Uint128) -> Vec<CosmosMsg> {
+    split_protocol_fee(utoken_fee, &fee_config.protocol_fee_recipients)
+        .into_iter()
+        .map(|(recipient, amount)| SendFee::new(recipient, amount.u128()).to_cosmos_msg())
+        .collect()
+}
+
+pub fn harvest(deps: DepsMut, env: Env) -> ContractResult<Response> {
     let withdraw_msgs = deps
         .querier
         .query_all_delegations(&env.contract.address)?
@@ -195,13 +270,106 @@ pub fn harvest(deps: DepsMut, env: Env) -> StdResult<Response> {
         })
         .collect::<Vec<_>>();
 
+    // Reward coins other than Token are swapped before the received Token is checked, so that the
+    // Token acquired is reinvested too
+    let has_swap_routes = State::default()
+        .swap_routes
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    let swap_msg = if has_swap_routes {
+        Some(CallbackMsg::Swap {}.into_cosmos_msg(&env.contract.address)?)
+    } else {
+        None
+    };
+
     Ok(Response::new()
         .add_messages(withdraw_msgs)
+        .add_optional_message(swap_msg)
         .add_message(check_received_coin_msg(&deps, &env, None)?)
         .add_message(CallbackMsg::Reinvest {}.into_cosmos_msg(&env.contract.address)?)
         .add_attribute("action", "erishub/harvest"))
 }
 
+/// Swap the reward coins that have a route configured into Token through the DEX router. The
+/// minimum amount received is the router's simulation minus the route's maximum spread.
+///
+/// Each swap is a `SubMsg` replying on error, so that a swap exceeding its slippage limit, or a
+/// router failing, is skipped and does not prevent the rewards from being reinvested.
+pub fn swap(deps: DepsMut, env: Env) -> ContractResult<Response> {
+    let state = State::default();
+
+    let routes = state
+        .swap_routes
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut msgs = vec![];
+    let mut event = Event::new("erishub/swapped");
+    for route in routes {
+        let offer_amount = deps.querier.query_balance(&env.contract.address, &route.denom)?.amount;
+        if offer_amount.is_zero() {
+            continue;
+        }
+
+        // A router that can't simulate the swap, e.g. because a pool was removed, is skipped like
+        // a failed swap, so that the other routes are still swapped
+        let simulation: SimulateSwapOperationsResponse = match deps.querier.query_wasm_smart(
+            &route.router,
+            &RouterQueryMsg::SimulateSwapOperations {
+                offer_amount,
+                operations: route.operations.clone(),
+            },
+        ) {
+            Ok(simulation) => simulation,
+            Err(err) => {
+                event =
+                    event.add_attribute("simulation_failed", route.denom + ": " + &err.to_string());
+                continue;
+            },
+        };
+        // The simulation can be skewed by trading against the router's pools earlier in the same
+        // transaction, so the minimum is never below the price configured by the owner
+        let minimum_receive = std::cmp::max(
+            simulation.amount.checked_sub(route.max_spread.checked_mul_uint(simulation.amount)?)?,
+            route.min_price.checked_mul_uint(offer_amount)?,
+        );
+
+        msgs.push(SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: route.router,
+                msg: to_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                    operations: route.operations,
+                    minimum_receive: Some(minimum_receive),
+                })?,
+                funds: vec![Coin::new(offer_amount.u128(), &route.denom)],
+            }),
+            2,
+        ));
+
+        event = event
+            .add_attribute("offer_coin", offer_amount.to_string() + route.denom.as_str())
+            .add_attribute("minimum_receive", minimum_receive.to_string() + CONTRACT_DENOM);
+    }
+
+    Ok(Response::new()
+        .add_submessages(msgs)
+        .add_event(event)
+        .add_attribute("action", "erishub/swap"))
+}
+
+/// Skip a swap that failed, e.g. because it exceeded its slippage limit
+pub fn skip_failed_swap(reply: Reply) -> ContractResult<Response> {
+    let error = reply.result.into_result().err().unwrap_or_default();
+    let event = Event::new("erishub/swap_failed").add_attribute("error", error);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/skip_failed_swap"))
+}
+
 /// This callback is used to take a current snapshot of the balance and add the received balance to the unlocked_coins state after the execution
 fn check_received_coin_msg(
     deps: &DepsMut,
@@ -228,11 +396,11 @@ fn check_received_coin_msg(
 
 /// NOTE:
 /// 1. When delegation Token here, we don't need to use a `SubMsg` to handle the received coins,
-/// because we have already withdrawn all claimable staking rewards previously in the same atomic
-/// execution.
+///    because we have already withdrawn all claimable staking rewards previously in the same atomic
+///    execution.
 /// 2. Same as with `bond`, in the latest implementation we only delegate staking rewards with the
-/// validator that has the smallest delegation amount.
-pub fn reinvest(deps: DepsMut, env: Env) -> StdResult<Response> {
+///    validator that has the smallest delegation amount relative to its target weight.
+pub fn reinvest(deps: DepsMut, env: Env) -> ContractResult<Response> {
     let state = State::default();
     let validators = state.validators.load(deps.storage)?;
     let mut unlocked_coins = state.unlocked_coins.load(deps.storage)?;
@@ -241,49 +409,67 @@ pub fn reinvest(deps: DepsMut, env: Env) -> StdResult<Response> {
     let utoken_available = unlocked_coins
         .iter()
         .find(|coin| coin.denom == CONTRACT_DENOM)
-        .ok_or_else(|| {
-            StdError::generic_err(format!("no {} available to be bonded", CONTRACT_DENOM))
-        })?
+        .ok_or_else(|| ContractError::NothingToReinvest(CONTRACT_DENOM.to_string()))?
         .amount;
 
     let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
-    let mut validator = &delegations[0].validator;
-    let mut amount = delegations[0].amount;
-    for d in &delegations[1..] {
-        if d.amount < amount {
-            validator = &d.validator;
-            amount = d.amount;
-        }
-    }
+    let strategy = load_strategy(deps.as_ref())?;
+    let validator = &strategy.validator_to_delegate(deps.as_ref(), &delegations)?.validator;
 
     let protocol_fee_amount = fee_config.protocol_reward_fee.checked_mul_uint(utoken_available)?;
-    let utoken_to_bond = utoken_available.saturating_sub(protocol_fee_amount);
+    let utoken_to_reinvest = utoken_available.saturating_sub(protocol_fee_amount);
+
+    // While the liquidity buffer is below its target, part of the rewards is kept in it. If it holds
+    // more than its target, e.g. because the target was lowered, the excess is delegated
+    let buffer_config = state.buffer_config.may_load(deps.storage)?.unwrap_or_default();
+    let utoken_buffered = state.buffer.may_load(deps.storage)?.unwrap_or_default();
+    let utoken_to_buffer = compute_buffer_refill(
+        utoken_to_reinvest,
+        buffer_config.reward_share,
+        utoken_buffered,
+        buffer_config.target,
+    )?;
+    let utoken_excess = utoken_buffered.saturating_sub(buffer_config.target);
+    state.buffer.save(deps.storage, &(utoken_buffered + utoken_to_buffer - utoken_excess))?;
 
-    let new_delegation = Delegation::new(validator, utoken_to_bond.u128());
+    let utoken_to_bond = utoken_to_reinvest - utoken_to_buffer + utoken_excess;
 
     unlocked_coins.retain(|coin| coin.denom != CONTRACT_DENOM);
     state.unlocked_coins.save(deps.storage, &unlocked_coins)?;
 
+    // Once the rewards are delegated or buffered, all of them are backing the Stake token
+    let stake_token = state.stake_token.load(deps.storage)?;
+    let ustake_supply = query_cw20_total_supply(&deps.querier, &stake_token)?;
+    let utoken_delegated: u128 = delegations.iter().map(|d| d.amount).sum();
+    save_exchange_rate_snapshot(
+        deps,
+        &env,
+        Uint128::new(utoken_delegated) + utoken_buffered + utoken_to_reinvest,
+        ustake_supply,
+    )?;
+
     let event = Event::new("erishub/harvested")
         .add_attribute("time", env.block.time.seconds().to_string())
         .add_attribute("height", env.block.height.to_string())
         .add_attribute("utoken_bonded", utoken_to_bond)
+        .add_attribute("utoken_buffered", utoken_to_buffer)
         .add_attribute("utoken_protocol_fee", protocol_fee_amount);
 
-    let mut msgs = vec![new_delegation.to_cosmos_msg()];
+    let mut msgs = vec![];
 
-    if !protocol_fee_amount.is_zero() {
-        let send_fee = SendFee::new(fee_config.protocol_fee_contract, protocol_fee_amount.u128());
-        msgs.push(send_fee.to_cosmos_msg());
+    if !utoken_to_bond.is_zero() {
+        msgs.push(Delegation::new(validator, utoken_to_bond.u128()).to_cosmos_msg());
     }
 
+    msgs.extend(send_protocol_fee_msgs(&fee_config, protocol_fee_amount));
+
     Ok(Response::new()
         .add_messages(msgs)
         .add_event(event)
         .add_attribute("action", "erishub/reinvest"))
 }
 
-pub fn callback_received_coin(deps: DepsMut, env: Env, snapshot: Coin) -> StdResult<Response> {
+pub fn callback_received_coin(deps: DepsMut, env: Env, snapshot: Coin) -> ContractResult<Response> {
     // in some cosmwasm versions the events are not received in the callback
     // so each time the contract can receive some coins from rewards we also need to check after receiving some and add them to the unlocked_coins
 
@@ -315,28 +501,30 @@ pub fn callback_received_coin(deps: DepsMut, env: Env, snapshot: Coin) -> StdRes
 pub fn queue_unbond(
     deps: DepsMut,
     env: Env,
+    sender: Addr,
     receiver: Addr,
     ustake_to_burn: Uint128,
-) -> StdResult<Response> {
+    auto_claim: Option<bool>,
+) -> ContractResult<Response> {
     let state = State::default();
 
     let mut pending_batch = state.pending_batch.load(deps.storage)?;
     pending_batch.ustake_to_burn += ustake_to_burn;
     state.pending_batch.save(deps.storage, &pending_batch)?;
 
-    state.unbond_requests.update(
-        deps.storage,
-        (pending_batch.id, &receiver),
-        |x| -> StdResult<_> {
-            let mut request = x.unwrap_or_else(|| UnbondRequest {
-                id: pending_batch.id,
-                user: receiver.clone(),
-                shares: Uint128::zero(),
-            });
-            request.shares += ustake_to_burn;
-            Ok(request)
+    add_to_unbond_request(deps.storage, pending_batch.id, &receiver, ustake_to_burn)?;
+
+    // Anyone can opt the receiver in, but only the receiver can opt out, so that queuing a dust
+    // amount on their behalf can not undo their choice
+    match auto_claim {
+        Some(true) => {
+            state.auto_claims.save(deps.storage, (pending_batch.id, &receiver), &Empty {})?
         },
-    )?;
+        Some(false) if sender == receiver => {
+            state.auto_claims.remove(deps.storage, (pending_batch.id, &receiver))
+        },
+        _ => (),
+    }
 
     let mut msgs: Vec<CosmosMsg> = vec![];
     let mut start_time = pending_batch.est_unbond_start_time.to_string();
@@ -344,7 +532,9 @@ pub fn queue_unbond(
         start_time = "immediate".to_string();
         msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: env.contract.address.clone().into(),
-            msg: to_binary(&ExecuteMsg::SubmitBatch {})?,
+            msg: to_binary(&ExecuteMsg::SubmitBatch {
+                force: None,
+            })?,
             funds: vec![],
         }));
     }
@@ -363,27 +553,219 @@ pub fn queue_unbond(
         .add_attribute("action", "erishub/queue_unbond"))
 }
 
-pub fn submit_batch(deps: DepsMut, env: Env) -> StdResult<Response> {
+/// Take back Stake token queued for unbonding, as long as the batch has not been submitted. The
+/// whole unbond request is cancelled if `amount` is not provided.
+pub fn cancel_unbond(
+    deps: DepsMut,
+    env: Env,
+    user: Addr,
+    amount: Option<Uint128>,
+) -> ContractResult<Response> {
+    let state = State::default();
+    let stake_token = state.stake_token.load(deps.storage)?;
+
+    let mut pending_batch = state.pending_batch.load(deps.storage)?;
+    let mut request = state
+        .unbond_requests
+        .may_load(deps.storage, (pending_batch.id, &user))?
+        .ok_or_else(|| ContractError::UnbondRequestNotFound {
+            id: pending_batch.id,
+            user: user.to_string(),
+        })?;
+
+    let ustake_to_return = amount.unwrap_or(request.shares);
+    if ustake_to_return.is_zero() || ustake_to_return > request.shares {
+        return Err(ContractError::CancelAmountTooHigh {
+            requested: ustake_to_return,
+            available: request.shares,
+        });
+    }
+
+    request.shares -= ustake_to_return;
+    if request.shares.is_zero() {
+        remove_unbond_request(deps.storage, pending_batch.id, &user)?;
+    } else {
+        state.unbond_requests.save(deps.storage, (pending_batch.id, &user), &request)?;
+    }
+
+    pending_batch.ustake_to_burn -= ustake_to_return;
+    state.pending_batch.save(deps.storage, &pending_batch)?;
+
+    let transfer_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: stake_token.into(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: user.to_string(),
+            amount: ustake_to_return,
+        })?,
+        funds: vec![],
+    });
+
+    let event = Event::new("erishub/unbond_cancelled")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("id", pending_batch.id.to_string())
+        .add_attribute("user", user)
+        .add_attribute("ustake_returned", ustake_to_return);
+
+    Ok(Response::new()
+        .add_message(transfer_msg)
+        .add_event(event)
+        .add_attribute("action", "erishub/cancel_unbond"))
+}
+
+/// Move the sender's unbond request in batch `id` to another address, merging it with any request
+/// the recipient already has in that batch
+pub fn transfer_unbond_request(
+    deps: DepsMut,
+    env: Env,
+    user: Addr,
+    id: u64,
+    recipient: String,
+) -> ContractResult<Response> {
+    let state = State::default();
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    if recipient == user {
+        return Err(ContractError::TransferToOwner);
+    }
+
+    let request = state.unbond_requests.may_load(deps.storage, (id, &user))?.ok_or_else(|| {
+        ContractError::UnbondRequestNotFound {
+            id,
+            user: user.to_string(),
+        }
+    })?;
+
+    // Requests traded as tokens are moved with `ExecuteMsg::TransferNft` instead. The recipient's
+    // request can't be merged into either, or its holder would receive the transferred shares
+    for owner in [&user, &recipient] {
+        if state.unbond_request_holders.may_load(deps.storage, (id, owner))?.is_some() {
+            return Err(ContractError::UnbondRequestHeldByAnother(token_id(id, owner)));
+        }
+    }
+
+    // Removing and saving through the `IndexedMap` keeps the `user` index up to date. The moved
+    // shares follow the recipient's auto-claim choice
+    remove_unbond_request(deps.storage, id, &user)?;
+    add_to_unbond_request(deps.storage, id, &recipient, request.shares)?;
+
+    let event = Event::new("erishub/unbond_request_moved")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("id", id.to_string())
+        .add_attribute("user", user)
+        .add_attribute("recipient", recipient)
+        .add_attribute("shares", request.shares);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/transfer_unbond_request"))
+}
+
+/// Redeem `ustake` immediately against the liquidity buffer. The instant unbond fee is left in the
+/// buffer, so it accrues to the stakers who remain.
+pub fn instant_unbond(
+    deps: DepsMut,
+    env: Env,
+    receiver: Addr,
+    ustake_to_burn: Uint128,
+) -> ContractResult<Response> {
+    let state = State::default();
+    let stake_token = state.stake_token.load(deps.storage)?;
+    let validators = state.validators.load(deps.storage)?;
+    let buffer_config = state.buffer_config.may_load(deps.storage)?.unwrap_or_default();
+    let utoken_buffered = state.buffer.may_load(deps.storage)?.unwrap_or_default();
+
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
+    let ustake_supply = query_cw20_total_supply(&deps.querier, &stake_token)?;
+
+    let utoken_to_unbond =
+        compute_unbond_amount(ustake_supply, ustake_to_burn, &delegations, utoken_buffered);
+    let utoken_fee = buffer_config.instant_unbond_fee.checked_mul_uint(utoken_to_unbond)?;
+    let utoken_to_send = utoken_to_unbond - utoken_fee;
+
+    if utoken_to_send > utoken_buffered {
+        return Err(ContractError::InsufficientBuffer {
+            available: utoken_buffered,
+            required: utoken_to_send,
+        });
+    }
+
+    state.buffer.save(deps.storage, &(utoken_buffered - utoken_to_send))?;
+
+    let burn_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: stake_token.into(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: ustake_to_burn,
+        })?,
+        funds: vec![],
+    });
+
+    let send_msg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: receiver.to_string(),
+        amount: vec![Coin::new(utoken_to_send.u128(), CONTRACT_DENOM)],
+    });
+
+    let event = Event::new("erishub/instant_unbonded")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("receiver", receiver)
+        .add_attribute("ustake_burned", ustake_to_burn)
+        .add_attribute("utoken_unbonded", utoken_to_send)
+        .add_attribute("utoken_fee", utoken_fee);
+
+    Ok(Response::new()
+        .add_message(burn_msg)
+        .add_message(send_msg)
+        .add_event(event)
+        .add_attribute("action", "erishub/instant_unbond"))
+}
+
+pub fn submit_batch(
+    mut deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    force: Option<bool>,
+) -> ContractResult<Response> {
     let state = State::default();
     let stake_token = state.stake_token.load(deps.storage)?;
     let validators = state.validators.load(deps.storage)?;
     let unbond_period = state.unbond_period.load(deps.storage)?;
     let pending_batch = state.pending_batch.load(deps.storage)?;
 
+    // Operators can submit the batch early, e.g. to line it up with the chain's unbonding entries
+    let force = force.unwrap_or(false);
+    if force {
+        state.assert_owner_or_role(deps.storage, &sender, Role::Operator)?;
+    }
+
     let current_time = env.block.time.seconds();
-    if current_time < pending_batch.est_unbond_start_time {
-        return Err(StdError::generic_err(format!(
-            "batch can only be submitted for unbonding after {}",
-            pending_batch.est_unbond_start_time
-        )));
+    if !force && current_time < pending_batch.est_unbond_start_time {
+        return Err(ContractError::BatchNotReady(pending_batch.est_unbond_start_time));
     }
 
     let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
     let ustake_supply = query_cw20_total_supply(&deps.querier, &stake_token)?;
+    let utoken_buffered = state.buffer.may_load(deps.storage)?.unwrap_or_default();
+
+    let utoken_to_unbond = compute_unbond_amount(
+        ustake_supply,
+        pending_batch.ustake_to_burn,
+        &delegations,
+        utoken_buffered,
+    );
+
+    // If more is unbonded than is delegated, the rest is taken out of the liquidity buffer. It is
+    // already in the contract's balance, where the batch will be paid out from
+    let utoken_delegated: u128 = delegations.iter().map(|d| d.amount).sum();
+    let utoken_from_buffer = utoken_to_unbond.saturating_sub(Uint128::new(utoken_delegated));
+    if !utoken_from_buffer.is_zero() {
+        state.buffer.save(deps.storage, &(utoken_buffered - utoken_from_buffer))?;
+    }
 
-    let utoken_to_unbond =
-        compute_unbond_amount(ustake_supply, pending_batch.ustake_to_burn, &delegations);
-    let new_undelegations = compute_undelegations(utoken_to_unbond, &delegations);
+    let new_undelegations = load_strategy(deps.as_ref())?.undelegations(
+        deps.as_ref(),
+        utoken_to_unbond - utoken_from_buffer,
+        &delegations,
+    )?;
 
     state.previous_batches.save(
         deps.storage,
@@ -407,6 +789,13 @@ pub fn submit_batch(deps: DepsMut, env: Env) -> StdResult<Response> {
         },
     )?;
 
+    save_exchange_rate_snapshot(
+        deps.branch(),
+        &env,
+        (Uint128::new(utoken_delegated) + utoken_buffered).saturating_sub(utoken_to_unbond),
+        ustake_supply - pending_batch.ustake_to_burn,
+    )?;
+
     let undelegate_msgs = new_undelegations.iter().map(|d| d.to_cosmos_msg()).collect::<Vec<_>>();
 
     let burn_msg = CosmosMsg::Wasm(WasmMsg::Execute {
@@ -432,7 +821,43 @@ pub fn submit_batch(deps: DepsMut, env: Env) -> StdResult<Response> {
         .add_attribute("action", "erishub/unbond"))
 }
 
-pub fn reconcile(deps: DepsMut, env: Env) -> StdResult<Response> {
+fn save_exchange_rate_snapshot(
+    deps: DepsMut,
+    env: &Env,
+    total_utoken: Uint128,
+    total_ustake: Uint128,
+) -> StdResult<()> {
+    State::default().save_exchange_rate(
+        deps.storage,
+        &ExchangeRateSnapshot {
+            time: env.block.time.seconds(),
+            total_utoken,
+            total_ustake,
+            exchange_rate: compute_exchange_rate(total_utoken, total_ustake),
+        },
+    )
+}
+
+pub fn reconcile(deps: DepsMut, env: Env) -> ContractResult<Response> {
+    let state = State::default();
+
+    let (batches, utoken_deducted) = compute_reconciliation(deps.as_ref(), &env)?;
+    if batches.is_empty() {
+        return Ok(Response::new());
+    }
+
+    for batch in &batches {
+        state.previous_batches.save(deps.storage, batch.id, batch)?;
+    }
+
+    Ok(Response::new()
+        .add_event(reconciled_event(&batches, utoken_deducted))
+        .add_attribute("action", "erishub/reconcile"))
+}
+
+/// Reconcile the batches that have finished unbonding but have not been reconciled yet. Returns
+/// the updated batches and the amount of `utoken` deducted from them, without saving anything.
+pub(crate) fn compute_reconciliation(deps: Deps, env: &Env) -> StdResult<(Vec<Batch>, Uint128)> {
     let state = State::default();
     let current_time = env.block.time.seconds();
 
@@ -457,42 +882,35 @@ pub fn reconcile(deps: DepsMut, env: Env) -> StdResult<Response> {
     let utoken_expected_received: Uint128 = batches.iter().map(|b| b.utoken_unclaimed).sum();
 
     if utoken_expected_received.is_zero() {
-        return Ok(Response::new());
+        return Ok((vec![], Uint128::zero()));
     }
 
     let unlocked_coins = state.unlocked_coins.load(deps.storage)?;
     let utoken_expected_unlocked = Coins(unlocked_coins).find(CONTRACT_DENOM).amount;
+    let utoken_expected_buffered = state.buffer.may_load(deps.storage)?.unwrap_or_default();
 
-    let utoken_expected = utoken_expected_received + utoken_expected_unlocked;
+    let utoken_expected =
+        utoken_expected_received + utoken_expected_unlocked + utoken_expected_buffered;
     let utoken_actual = deps.querier.query_balance(&env.contract.address, CONTRACT_DENOM)?.amount;
 
     if utoken_actual >= utoken_expected {
         mark_reconciled_batches(&mut batches);
-        for batch in &batches {
-            state.previous_batches.save(deps.storage, batch.id, batch)?;
-        }
-        let ids = batches.iter().map(|b| b.id.to_string()).collect::<Vec<_>>().join(",");
-        let event = Event::new("erishub/reconciled")
-            .add_attribute("ids", ids)
-            .add_attribute("utoken_deducted", "0");
-        return Ok(Response::new().add_event(event).add_attribute("action", "erishub/reconcile"));
+        return Ok((batches, Uint128::zero()));
     }
 
     let utoken_to_deduct = utoken_expected - utoken_actual;
 
     reconcile_batches(&mut batches, utoken_to_deduct);
 
-    for batch in &batches {
-        state.previous_batches.save(deps.storage, batch.id, batch)?;
-    }
+    Ok((batches, utoken_to_deduct))
+}
 
+fn reconciled_event(batches: &[Batch], utoken_deducted: Uint128) -> Event {
     let ids = batches.iter().map(|b| b.id.to_string()).collect::<Vec<_>>().join(",");
 
-    let event = Event::new("erishub/reconciled")
+    Event::new("erishub/reconciled")
         .add_attribute("ids", ids)
-        .add_attribute("utoken_deducted", utoken_to_deduct.to_string());
-
-    Ok(Response::new().add_event(event).add_attribute("action", "erishub/reconcile"))
+        .add_attribute("utoken_deducted", utoken_deducted.to_string())
 }
 
 pub fn withdraw_unbonded(
@@ -500,63 +918,69 @@ pub fn withdraw_unbonded(
     env: Env,
     user: Addr,
     receiver: Addr,
-) -> StdResult<Response> {
+    batch_ids: Option<Vec<u64>>,
+    limit: Option<u32>,
+) -> ContractResult<Response> {
     let state = State::default();
     let current_time = env.block.time.seconds();
 
-    // NOTE: If the user has too many unclaimed requests, this may not fit in the WASM memory...
-    // However, this is practically never going to happen. Who would create hundreds of unbonding
-    // requests and never claim them?
-    let requests = state
-        .unbond_requests
-        .idx
-        .user
-        .prefix(user.to_string())
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|item| {
-            let (_, v) = item?;
-            Ok(v)
-        })
-        .collect::<StdResult<Vec<_>>>()?;
+    // Batches that have finished unbonding are reconciled first, so that they can be withdrawn
+    // from without invoking `ExecuteMsg::Reconcile` beforehand
+    let (reconciled_batches, utoken_deducted) = compute_reconciliation(deps.as_ref(), &env)?;
+    for batch in &reconciled_batches {
+        state.previous_batches.save(deps.storage, batch.id, batch)?;
+    }
+    let reconciled_event = if reconciled_batches.is_empty() {
+        None
+    } else {
+        Some(reconciled_event(&reconciled_batches, utoken_deducted))
+    };
 
     // NOTE: Token in the following batches are withdrawn it the batch:
     // - is a _previous_ batch, not a _pending_ batch
     // - is reconciled
     // - has finished unbonding
-    // If not sure whether the batches have been reconciled, the user should first invoke `ExecuteMsg::Reconcile`
-    // before withdrawing.
+    //
+    // Unbond requests are paid to the account currently holding them, which is not necessarily the
+    // user who queued them. Accounts with many requests can bound the work done here by selecting
+    // `batch_ids` or setting a `limit`.
+    let requests = withdrawable_unbond_requests(
+        deps.storage,
+        &user,
+        current_time,
+        &[],
+        batch_ids.as_deref(),
+        limit,
+    )?;
+
     let mut total_utoken_to_refund = Uint128::zero();
     let mut ids: Vec<String> = vec![];
     for request in &requests {
-        if let Ok(mut batch) = state.previous_batches.load(deps.storage, request.id) {
-            if batch.reconciled && batch.est_unbond_end_time < current_time {
-                let utoken_to_refund =
-                    batch.utoken_unclaimed.multiply_ratio(request.shares, batch.total_shares);
+        let mut batch = state.previous_batches.load(deps.storage, request.id)?;
 
-                ids.push(request.id.to_string());
+        total_utoken_to_refund += withdraw_from_batch(&mut batch, request.shares);
+        ids.push(request.id.to_string());
 
-                total_utoken_to_refund += utoken_to_refund;
-                batch.total_shares -= request.shares;
-                batch.utoken_unclaimed -= utoken_to_refund;
-
-                if batch.total_shares.is_zero() {
-                    state.previous_batches.remove(deps.storage, request.id)?;
-                } else {
-                    state.previous_batches.save(deps.storage, batch.id, &batch)?;
-                }
-
-                state.unbond_requests.remove(deps.storage, (request.id, &user))?;
-            }
+        if batch.total_shares.is_zero() {
+            state.previous_batches.remove(deps.storage, request.id)?;
+        } else {
+            state.previous_batches.save(deps.storage, batch.id, &batch)?;
         }
+
+        remove_unbond_request(deps.storage, request.id, &request.user)?;
     }
 
     if total_utoken_to_refund.is_zero() {
-        return Err(StdError::generic_err("withdrawable amount is zero"));
+        return Err(ContractError::NothingToWithdraw);
     }
 
+    let fee_config = state.fee_config.load(deps.storage)?;
+    let (utoken_to_receiver, utoken_withdrawal_fee) =
+        deduct_withdrawal_fee(&fee_config, total_utoken_to_refund)?;
+
     let refund_msg = CosmosMsg::Bank(BankMsg::Send {
         to_address: receiver.clone().into(),
-        amount: vec![Coin::new(total_utoken_to_refund.u128(), CONTRACT_DENOM)],
+        amount: vec![Coin::new(utoken_to_receiver.u128(), CONTRACT_DENOM)],
     });
 
     let event = Event::new("erishub/unbonded_withdrawn")
@@ -565,25 +989,147 @@ pub fn withdraw_unbonded(
         .add_attribute("ids", ids.join(","))
         .add_attribute("user", user)
         .add_attribute("receiver", receiver)
-        .add_attribute("utoken_refunded", total_utoken_to_refund);
+        .add_attribute("utoken_refunded", total_utoken_to_refund)
+        .add_attribute("utoken_withdrawal_fee", utoken_withdrawal_fee);
 
     Ok(Response::new()
         .add_message(refund_msg)
+        .add_messages(send_protocol_fee_msgs(&fee_config, utoken_withdrawal_fee))
+        .add_events(reconciled_event)
         .add_event(event)
         .add_attribute("action", "erishub/withdraw_unbonded"))
 }
 
+pub fn distribute_unbonded(
+    deps: DepsMut,
+    env: Env,
+    limit: Option<u32>,
+) -> ContractResult<Response> {
+    let state = State::default();
+    let current_time = env.block.time.seconds();
+    let limit = limit.unwrap_or(DEFAULT_DISTRIBUTION_LIMIT).min(MAX_DISTRIBUTION_LIMIT) as usize;
+
+    let (reconciled_batches, utoken_deducted) = compute_reconciliation(deps.as_ref(), &env)?;
+    for batch in &reconciled_batches {
+        state.previous_batches.save(deps.storage, batch.id, batch)?;
+    }
+    let reconciled_event = if reconciled_batches.is_empty() {
+        None
+    } else {
+        Some(reconciled_event(&reconciled_batches, utoken_deducted))
+    };
+
+    // Batches finish unbonding in the order of their IDs, so the requests that can be paid out are
+    // the first ones
+    let mut keys = vec![];
+    for key in state.auto_claims.keys(deps.storage, None, None, Order::Ascending) {
+        let (id, user) = key?;
+        let finished = match state.previous_batches.may_load(deps.storage, id)? {
+            Some(batch) => batch.reconciled && batch.est_unbond_end_time < current_time,
+            None => false,
+        };
+        if !finished {
+            break;
+        }
+        keys.push((id, user));
+        if keys.len() >= limit {
+            break;
+        }
+    }
+
+    if keys.is_empty() {
+        return Err(ContractError::NothingToDistribute);
+    }
+
+    let mut refunds: Vec<(Addr, Uint128)> = vec![];
+    let mut ids: Vec<String> = vec![];
+    for (id, user) in &keys {
+        let holder = query_holder(deps.storage, *id, user)?;
+        let request = state.unbond_requests.load(deps.storage, (*id, user))?;
+        let mut batch = state.previous_batches.load(deps.storage, *id)?;
+
+        let utoken_to_refund = withdraw_from_batch(&mut batch, request.shares);
+        ids.push(id.to_string());
+
+        if batch.total_shares.is_zero() {
+            state.previous_batches.remove(deps.storage, *id)?;
+        } else {
+            state.previous_batches.save(deps.storage, *id, &batch)?;
+        }
+
+        remove_unbond_request(deps.storage, *id, user)?;
+
+        match refunds.iter_mut().find(|(addr, _)| *addr == holder) {
+            Some((_, amount)) => *amount += utoken_to_refund,
+            None => refunds.push((holder, utoken_to_refund)),
+        }
+    }
+
+    ids.dedup();
+
+    // The withdrawal fee is charged on each holder's refund
+    let fee_config = state.fee_config.load(deps.storage)?;
+    let mut utoken_withdrawal_fee = Uint128::zero();
+    for (_, amount) in refunds.iter_mut() {
+        let (utoken_to_holder, fee) = deduct_withdrawal_fee(&fee_config, *amount)?;
+        utoken_withdrawal_fee += fee;
+        *amount = utoken_to_holder;
+    }
+
+    let refund_msgs = refunds
+        .iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(holder, amount)| {
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: holder.to_string(),
+                amount: vec![Coin::new(amount.u128(), CONTRACT_DENOM)],
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let event = Event::new("erishub/unbonded_distributed")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("ids", ids.join(","))
+        .add_attributes(refunds.iter().map(|(holder, amount)| (holder.to_string(), *amount)))
+        .add_attribute("utoken_withdrawal_fee", utoken_withdrawal_fee);
+
+    Ok(Response::new()
+        .add_messages(refund_msgs)
+        .add_messages(send_protocol_fee_msgs(&fee_config, utoken_withdrawal_fee))
+        .add_events(reconciled_event)
+        .add_event(event)
+        .add_attribute("action", "erishub/distribute_unbonded"))
+}
+
 //--------------------------------------------------------------------------------------------------
 // Ownership and management logics
 //--------------------------------------------------------------------------------------------------
 
-pub fn rebalance(deps: DepsMut, env: Env) -> StdResult<Response> {
+pub fn rebalance(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    min_deviation: Option<Uint128>,
+    max_redelegations: Option<u32>,
+    force: Option<bool>,
+) -> ContractResult<Response> {
     let state = State::default();
-    let validators = state.validators.load(deps.storage)?;
 
-    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
+    let force = force.unwrap_or(false);
+    if force {
+        state.assert_owner_or_role(deps.storage, &sender, Role::Operator)?;
+    }
+
+    let new_redelegations =
+        plan_rebalancing(deps.as_ref(), &env, min_deviation, max_redelegations, force)?;
 
-    let new_redelegations = compute_redelegations_for_rebalancing(&delegations);
+    let unbond_period = state.unbond_period.load(deps.storage)?;
+    state.save_redelegations(
+        deps.storage,
+        &new_redelegations,
+        env.block.time.seconds() + unbond_period,
+    )?;
 
     let redelegate_msgs = new_redelegations.iter().map(|rd| rd.to_cosmos_msg()).collect::<Vec<_>>();
 
@@ -598,45 +1144,62 @@ pub fn rebalance(deps: DepsMut, env: Env) -> StdResult<Response> {
         .add_attribute("action", "erishub/rebalance"))
 }
 
-pub fn add_validator(deps: DepsMut, sender: Addr, validator: String) -> StdResult<Response> {
+pub fn add_validator(
+    deps: DepsMut,
+    sender: Addr,
+    validator: String,
+    weight: Option<u64>,
+) -> ContractResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
 
-    state.validators.update(deps.storage, |mut validators| {
-        if validators.contains(&validator) {
-            return Err(StdError::generic_err("validator is already whitelisted"));
-        }
-        validators.push(validator.clone());
-        Ok(validators)
-    })?;
+    let mut validators = state.validators.load(deps.storage)?;
+    if validators.contains(&validator) {
+        return Err(ContractError::ValidatorAlreadyWhitelisted(validator));
+    }
+
+    let commission = query_active_validator(&deps.querier, &validator)?
+        .ok_or_else(|| ContractError::ValidatorNotActive(validator.clone()))?
+        .commission;
+    let max_commission = state.load_max_commission(deps.storage)?;
+    if commission > max_commission {
+        return Err(ContractError::CommissionTooHigh {
+            commission,
+            max_commission,
+        });
+    }
+
+    validators.push(validator.clone());
+    state.validators.save(deps.storage, &validators)?;
+
+    let weight = weight.unwrap_or(DEFAULT_VALIDATOR_WEIGHT);
+    state.validator_weights.save(deps.storage, &validator, &weight)?;
 
-    let event = Event::new("erishub/validator_added").add_attribute("validator", validator);
+    let event = Event::new("erishub/validator_added")
+        .add_attribute("validator", validator)
+        .add_attribute("weight", weight.to_string());
 
     Ok(Response::new().add_event(event).add_attribute("action", "erishub/add_validator"))
 }
 
 pub fn remove_validator(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     sender: Addr,
     validator: String,
-) -> StdResult<Response> {
+) -> ContractResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
 
-    let validators = state.validators.update(deps.storage, |mut validators| {
-        if !validators.contains(&validator) {
-            return Err(StdError::generic_err("validator is not already whitelisted"));
-        }
-        validators.retain(|v| *v != validator);
-        Ok(validators)
-    })?;
+    let validators = state.validators.load(deps.storage)?;
+    if !validators.contains(&validator) {
+        return Err(ContractError::ValidatorNotWhitelisted(validator));
+    }
 
-    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
-    let delegation_to_remove = query_delegation(&deps.querier, &validator, &env.contract.address)?;
-    let new_redelegations = compute_redelegations_for_removal(&delegation_to_remove, &delegations);
+    let new_redelegations =
+        remove_validators_from_whitelist(deps.branch(), &env, std::slice::from_ref(&validator))?;
 
     let redelegate_msgs = new_redelegations.iter().map(|d| d.to_cosmos_msg()).collect::<Vec<_>>();
 
@@ -649,7 +1212,369 @@ pub fn remove_validator(
         .add_attribute("action", "erishub/remove_validator"))
 }
 
-pub fn transfer_ownership(deps: DepsMut, sender: Addr, new_owner: String) -> StdResult<Response> {
+/// Remove whitelisted validators that are no longer part of the active validator set, e.g. because
+/// they have been jailed or tombstoned, or that have raised their commission above the maximum, and
+/// redelegate their stake to the remaining validators. Anyone can invoke this.
+pub fn check_validators(mut deps: DepsMut, env: Env) -> ContractResult<Response> {
+    let state = State::default();
+    let validators = state.validators.load(deps.storage)?;
+    let max_commission = state.load_max_commission(deps.storage)?;
+
+    // Jailed and tombstoned validators still exist, but are not in the active set
+    let active_validators = deps.querier.query_all_validators()?;
+
+    let mut inactive_validators: Vec<String> = vec![];
+    let mut overcharging_validators: Vec<String> = vec![];
+    for validator in &validators {
+        match active_validators.iter().find(|v| v.address == *validator) {
+            None => inactive_validators.push(validator.clone()),
+            Some(v) if v.commission > max_commission => {
+                overcharging_validators.push(validator.clone())
+            },
+            Some(_) => (),
+        }
+    }
+
+    let validators_to_remove =
+        [inactive_validators.as_slice(), overcharging_validators.as_slice()].concat();
+
+    if validators_to_remove.is_empty() {
+        return Err(ContractError::NoValidatorToRemove);
+    }
+    if validators_to_remove.len() == validators.len() {
+        return Err(ContractError::CannotRemoveAllValidators);
+    }
+
+    // Validators that can not be redelegated from yet are left for a later check
+    let blocked_validators = state.load_blocked_validators(
+        deps.storage,
+        &validators_to_remove,
+        env.block.time.seconds(),
+    )?;
+    inactive_validators.retain(|v| !blocked_validators.contains(v));
+    overcharging_validators.retain(|v| !blocked_validators.contains(v));
+
+    let validators_to_remove =
+        [inactive_validators.as_slice(), overcharging_validators.as_slice()].concat();
+
+    if validators_to_remove.is_empty() {
+        return Err(ContractError::ValidatorsBlocked(blocked_validators.join(",")));
+    }
+
+    let new_redelegations =
+        remove_validators_from_whitelist(deps.branch(), &env, &validators_to_remove)?;
+
+    let redelegate_msgs = new_redelegations.iter().map(|d| d.to_cosmos_msg()).collect::<Vec<_>>();
+
+    let event = Event::new("erishub/validators_checked")
+        .add_attribute("inactive_removed", inactive_validators.join(","))
+        .add_attribute("commission_removed", overcharging_validators.join(","))
+        .add_attribute("skipped", blocked_validators.join(","));
+
+    Ok(Response::new()
+        .add_messages(redelegate_msgs)
+        .add_message(check_received_coin_msg(&deps, &env, None)?)
+        .add_event(event)
+        .add_attribute("action", "erishub/check_validators"))
+}
+
+/// Remove validators from the whitelist, and compute the redelegations that move their stake to the
+/// validators that remain. The redelegations computed for a removed validator are taken into account
+/// when computing those of the next one.
+fn remove_validators_from_whitelist(
+    deps: DepsMut,
+    env: &Env,
+    validators_to_remove: &[String],
+) -> ContractResult<Vec<Redelegation>> {
+    let state = State::default();
+    let current_time = env.block.time.seconds();
+
+    for validator in validators_to_remove {
+        let completion_time =
+            state.redelegation_completion_times.may_load(deps.storage, validator)?.unwrap_or(0);
+        if completion_time > current_time {
+            return Err(ContractError::RedelegationBlocked {
+                validator: validator.clone(),
+                completion_time,
+            });
+        }
+    }
+
+    let validators = state.validators.update(deps.storage, |mut validators| -> StdResult<_> {
+        validators.retain(|v| !validators_to_remove.contains(v));
+        Ok(validators)
+    })?;
+    for validator in validators_to_remove {
+        state.validator_weights.remove(deps.storage, validator);
+    }
+    remove_validator_votes(deps.storage, validators_to_remove)?;
+
+    let strategy = load_strategy(deps.as_ref())?;
+    let mut delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
+
+    let mut new_redelegations: Vec<Redelegation> = vec![];
+    for validator in validators_to_remove {
+        let delegation_to_remove =
+            query_delegation(&deps.querier, validator, &env.contract.address)?;
+        let redelegations = strategy.redelegations_for_removal(
+            deps.as_ref(),
+            &delegation_to_remove,
+            &delegations,
+        )?;
+
+        for rd in &redelegations {
+            if let Some(d) = delegations.iter_mut().find(|d| d.validator == rd.dst) {
+                d.amount += rd.amount;
+            }
+        }
+        new_redelegations.extend(redelegations);
+    }
+
+    let unbond_period = state.unbond_period.load(deps.storage)?;
+    state.save_redelegations(deps.storage, &new_redelegations, current_time + unbond_period)?;
+
+    Ok(new_redelegations)
+}
+
+pub fn update_validator_weights(
+    deps: DepsMut,
+    sender: Addr,
+    weights: Vec<(String, u64)>,
+) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    validate_validator_weights(deps.storage, &weights)?;
+
+    let mut event = Event::new("erishub/validator_weights_updated");
+    for (validator, weight) in weights {
+        state.validator_weights.save(deps.storage, &validator, &weight)?;
+        event = event.add_attribute(validator, weight.to_string());
+    }
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/update_validator_weights"))
+}
+
+pub fn update_liquidity_buffer(
+    deps: DepsMut,
+    sender: Addr,
+    config: LiquidityBufferConfig,
+) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    validate_liquidity_buffer(&config)?;
+
+    state.buffer_config.save(deps.storage, &config)?;
+
+    let event = Event::new("erishub/liquidity_buffer_updated")
+        .add_attribute("target", config.target)
+        .add_attribute("bond_share", config.bond_share.to_string())
+        .add_attribute("reward_share", config.reward_share.to_string())
+        .add_attribute("instant_unbond_fee", config.instant_unbond_fee.to_string());
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/update_liquidity_buffer"))
+}
+
+/// Validate that the validators whose weights are updated are all whitelisted
+pub(crate) fn validate_validator_weights(
+    storage: &dyn Storage,
+    weights: &[(String, u64)],
+) -> ContractResult<()> {
+    let validators = State::default().validators.load(storage)?;
+    for (validator, _) in weights {
+        if !validators.contains(validator) {
+            return Err(ContractError::ValidatorNotWhitelisted(validator.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Validate that the buffer shares are at most 100%, and the instant unbond fee within its cap
+pub(crate) fn validate_liquidity_buffer(config: &LiquidityBufferConfig) -> ContractResult<()> {
+    if config.bond_share > Decimal::one() || config.reward_share > Decimal::one() {
+        return Err(ContractError::InvalidBufferShares);
+    }
+    if config.instant_unbond_fee > get_instant_unbond_fee_cap() {
+        return Err(ContractError::GreaterThanMax("instant_unbond_fee".to_string()));
+    }
+    Ok(())
+}
+
+pub fn update_swap_routes(
+    deps: DepsMut,
+    sender: Addr,
+    add: Vec<SwapRoute>,
+    remove: Vec<String>,
+) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    for denom in &remove {
+        state.swap_routes.remove(deps.storage, denom);
+    }
+
+    for mut route in add {
+        if route.denom == CONTRACT_DENOM {
+            return Err(ContractError::CannotSwapContractDenom(CONTRACT_DENOM.to_string()));
+        }
+
+        // The operations must form a path from the reward denom to Token
+        let mut denom = &route.denom;
+        for operation in &route.operations {
+            if operation.offer_denom != *denom {
+                return Err(ContractError::InvalidSwapRoute(route.denom));
+            }
+            denom = &operation.ask_denom;
+        }
+        if denom != CONTRACT_DENOM {
+            return Err(ContractError::InvalidSwapRoute(route.denom));
+        }
+
+        if route.max_spread > Decimal::one() {
+            return Err(ContractError::GreaterThanOne("max_spread".to_string()));
+        }
+        if route.min_price.is_zero() {
+            return Err(ContractError::ZeroSwapMinPrice(route.denom));
+        }
+
+        route.router = deps.api.addr_validate(&route.router)?.into();
+        state.swap_routes.save(deps.storage, &route.denom.clone(), &route)?;
+    }
+
+    let denoms = state
+        .swap_routes
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let event = Event::new("erishub/swap_routes_updated").add_attribute("denoms", denoms.join(","));
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/update_swap_routes"))
+}
+
+/// Validate the addresses of the protocol fee recipients, and that their shares add up to 100%
+fn validate_protocol_fee_recipients(
+    api: &dyn Api,
+    recipients: Vec<(String, Decimal)>,
+) -> ContractResult<Vec<(Addr, Decimal)>> {
+    let mut validated: Vec<(Addr, Decimal)> = vec![];
+    for (address, share) in recipients {
+        let address = api.addr_validate(&address)?;
+        if share.is_zero() {
+            return Err(ContractError::ZeroProtocolFeeShare(address.into()));
+        }
+        if validated.iter().any(|(a, _)| *a == address) {
+            return Err(ContractError::DuplicateProtocolFeeRecipient(address.into()));
+        }
+        validated.push((address, share));
+    }
+
+    let total_share = validated
+        .iter()
+        .try_fold(Decimal::zero(), |total, (_, share)| total.checked_add(*share))?;
+    if total_share != Decimal::one() {
+        return Err(ContractError::InvalidProtocolFeeShares);
+    }
+
+    Ok(validated)
+}
+
+pub fn grant_role(
+    deps: DepsMut,
+    sender: Addr,
+    role: Role,
+    account: String,
+) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let account = deps.api.addr_validate(&account)?;
+    state.roles.save(deps.storage, (role.as_str(), &account), &Empty {})?;
+
+    let event = Event::new("erishub/role_granted")
+        .add_attribute("role", role.as_str())
+        .add_attribute("account", account);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/grant_role"))
+}
+
+pub fn revoke_role(
+    deps: DepsMut,
+    sender: Addr,
+    role: Role,
+    account: String,
+) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let account = deps.api.addr_validate(&account)?;
+    if !state.has_role(deps.storage, &account, role)? {
+        return Err(ContractError::RoleNotHeld {
+            account: account.into(),
+            role,
+        });
+    }
+    state.roles.remove(deps.storage, (role.as_str(), &account));
+
+    let event = Event::new("erishub/role_revoked")
+        .add_attribute("role", role.as_str())
+        .add_attribute("account", account);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/revoke_role"))
+}
+
+pub fn pause(deps: DepsMut, sender: Addr, flags: PauseState) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner_or_role(deps.storage, &sender, Role::Guardian)?;
+
+    let mut paused = state.pause_state.may_load(deps.storage)?.unwrap_or_default();
+    paused.bond |= flags.bond;
+    paused.unbond |= flags.unbond;
+    paused.withdraw |= flags.withdraw;
+    paused.harvest |= flags.harvest;
+    state.pause_state.save(deps.storage, &paused)?;
+
+    Ok(Response::new()
+        .add_event(pause_event("erishub/paused", &paused))
+        .add_attribute("action", "erishub/pause"))
+}
+
+pub fn unpause(deps: DepsMut, sender: Addr, flags: PauseState) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let mut paused = state.pause_state.may_load(deps.storage)?.unwrap_or_default();
+    paused.bond &= !flags.bond;
+    paused.unbond &= !flags.unbond;
+    paused.withdraw &= !flags.withdraw;
+    paused.harvest &= !flags.harvest;
+    state.pause_state.save(deps.storage, &paused)?;
+
+    Ok(Response::new()
+        .add_event(pause_event("erishub/unpaused", &paused))
+        .add_attribute("action", "erishub/unpause"))
+}
+
+fn pause_event(ty: &str, paused: &PauseState) -> Event {
+    Event::new(ty)
+        .add_attribute("bond", paused.bond.to_string())
+        .add_attribute("unbond", paused.unbond.to_string())
+        .add_attribute("withdraw", paused.withdraw.to_string())
+        .add_attribute("harvest", paused.harvest.to_string())
+}
+
+pub fn transfer_ownership(
+    deps: DepsMut,
+    sender: Addr,
+    new_owner: String,
+) -> ContractResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
@@ -658,14 +1583,14 @@ pub fn transfer_ownership(deps: DepsMut, sender: Addr, new_owner: String) -> Std
     Ok(Response::new().add_attribute("action", "erishub/transfer_ownership"))
 }
 
-pub fn accept_ownership(deps: DepsMut, sender: Addr) -> StdResult<Response> {
+pub fn accept_ownership(deps: DepsMut, sender: Addr) -> ContractResult<Response> {
     let state = State::default();
 
     let previous_owner = state.owner.load(deps.storage)?;
     let new_owner = state.new_owner.load(deps.storage)?;
 
     if sender != new_owner {
-        return Err(StdError::generic_err("unauthorized: sender is not new owner"));
+        return Err(ContractError::UnauthorizedNewOwner);
     }
 
     state.owner.save(deps.storage, &sender)?;
@@ -681,29 +1606,89 @@ pub fn accept_ownership(deps: DepsMut, sender: Addr) -> StdResult<Response> {
 pub fn update_config(
     deps: DepsMut,
     sender: Addr,
-    protocol_fee_contract: Option<String>,
-    protocol_reward_fee: Option<Decimal>,
-) -> StdResult<Response> {
+    msg: UpdateConfigMsg,
+) -> ContractResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
 
-    if protocol_fee_contract.is_some() || protocol_reward_fee.is_some() {
-        let mut fee_config = state.fee_config.load(deps.storage)?;
+    validate_config_update(deps.api, &msg)?;
+
+    if let Some(max_commission) = msg.max_commission {
+        state.max_commission.save(deps.storage, &max_commission)?;
+    }
+
+    if let Some(min_redelegation) = msg.min_redelegation {
+        state.min_redelegation.save(deps.storage, &min_redelegation)?;
+    }
+
+    if let Some(delegation_strategy) = msg.delegation_strategy {
+        state
+            .delegation_strategy
+            .save(deps.storage, &validate_strategy_config(deps.api, delegation_strategy)?)?;
+    }
+
+    let mut fee_config = state.fee_config.load(deps.storage)?;
+
+    if let Some(protocol_fee_recipients) = msg.protocol_fee_recipients {
+        fee_config.protocol_fee_recipients =
+            validate_protocol_fee_recipients(deps.api, protocol_fee_recipients)?;
+    }
+
+    if let Some(protocol_reward_fee) = msg.protocol_reward_fee {
+        fee_config.protocol_reward_fee = protocol_reward_fee;
+    }
 
-        if let Some(protocol_fee_contract) = protocol_fee_contract {
-            fee_config.protocol_fee_contract = deps.api.addr_validate(&protocol_fee_contract)?;
+    if let Some(deposit_fee) = msg.deposit_fee {
+        fee_config.deposit_fee = deposit_fee;
+    }
+
+    if let Some(deposit_fee_source) = msg.deposit_fee_source {
+        fee_config.deposit_fee_source = deposit_fee_source;
+    }
+
+    if let Some(withdrawal_fee) = msg.withdrawal_fee {
+        fee_config.withdrawal_fee = withdrawal_fee;
+    }
+
+    state.fee_config.save(deps.storage, &fee_config)?;
+
+    Ok(Response::new().add_attribute("action", "erishub/update_config"))
+}
+
+/// Validate the values of a config update against their caps
+pub(crate) fn validate_config_update(api: &dyn Api, msg: &UpdateConfigMsg) -> ContractResult<()> {
+    if let Some(max_commission) = msg.max_commission {
+        if max_commission > Decimal::one() {
+            return Err(ContractError::GreaterThanOne("max_commission".to_string()));
         }
+    }
 
-        if let Some(protocol_reward_fee) = protocol_reward_fee {
-            if protocol_reward_fee.gt(&get_reward_fee_cap()) {
-                return Err(StdError::generic_err("'protocol_reward_fee' greater than max"));
-            }
-            fee_config.protocol_reward_fee = protocol_reward_fee;
+    if let Some(delegation_strategy) = &msg.delegation_strategy {
+        validate_strategy_config(api, delegation_strategy.clone())?;
+    }
+
+    if let Some(protocol_fee_recipients) = &msg.protocol_fee_recipients {
+        validate_protocol_fee_recipients(api, protocol_fee_recipients.clone())?;
+    }
+
+    if let Some(protocol_reward_fee) = msg.protocol_reward_fee {
+        if protocol_reward_fee.gt(&get_reward_fee_cap()) {
+            return Err(ContractError::GreaterThanMax("protocol_reward_fee".to_string()));
         }
+    }
 
-        state.fee_config.save(deps.storage, &fee_config)?;
+    if let Some(deposit_fee) = msg.deposit_fee {
+        if deposit_fee.gt(&get_deposit_fee_cap()) {
+            return Err(ContractError::GreaterThanMax("deposit_fee".to_string()));
+        }
     }
 
-    Ok(Response::new().add_attribute("action", "erishub/update_config"))
+    if let Some(withdrawal_fee) = msg.withdrawal_fee {
+        if withdrawal_fee.gt(&get_withdrawal_fee_cap()) {
+            return Err(ContractError::GreaterThanMax("withdrawal_fee".to_string()));
+        }
+    }
+
+    Ok(())
 }
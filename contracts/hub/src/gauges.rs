@@ -0,0 +1,284 @@
+use cosmwasm_std::{
+    to_binary, Addr, CosmosMsg, Deps, DepsMut, Env, Event, Response, StdResult, Storage, Uint128,
+    WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+
+use eris_staking::hub::{UserVotes, ValidatorVotesResponse};
+
+use crate::error::{ContractError, ContractResult};
+use crate::state::State;
+
+/// Votes and tallied weights are expressed in basis points
+const MAX_BPS: u16 = 10000;
+
+//--------------------------------------------------------------------------------------------------
+// Voting logics
+//--------------------------------------------------------------------------------------------------
+
+/// Cast the sender's votes, replacing any votes they previously cast. The voting power is the amount
+/// of Stake token the sender has locked in the hub with `ReceiveMsg::LockVotingPower`, so that the
+/// same tokens can't be transferred and voted with again by another account.
+pub fn vote(deps: DepsMut, sender: Addr, votes: Vec<(String, u16)>) -> ContractResult<Response> {
+    let state = State::default();
+    let validators = state.validators.load(deps.storage)?;
+
+    let mut total_bps: u32 = 0;
+    for (i, (validator, bps)) in votes.iter().enumerate() {
+        if !validators.contains(validator) {
+            return Err(ContractError::ValidatorNotWhitelisted(validator.clone()));
+        }
+        if votes[..i].iter().any(|(v, _)| v == validator) {
+            return Err(ContractError::DuplicateVote(validator.clone()));
+        }
+        total_bps += *bps as u32;
+    }
+
+    if total_bps > MAX_BPS as u32 {
+        return Err(ContractError::VotesExceedMax {
+            max_bps: MAX_BPS,
+            total_bps,
+        });
+    }
+
+    let power = load_user_votes(deps.storage, &sender)?.power;
+
+    apply_user_votes(deps.storage, &sender, power, votes)?;
+
+    let event = Event::new("erishub/voted")
+        .add_attribute("user", sender)
+        .add_attribute("power", power)
+        .add_attribute("bps", total_bps.to_string());
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/vote"))
+}
+
+/// Add the Stake token sent to the user's voting power, keeping their current votes. Votes for
+/// validators removed from the whitelist since they were cast are dropped
+pub fn lock_voting_power(deps: DepsMut, user: Addr, amount: Uint128) -> ContractResult<Response> {
+    let user_votes = load_user_votes(deps.storage, &user)?;
+    let power = user_votes.power.checked_add(amount)?;
+    let votes = counted_votes(deps.storage, &user, user_votes.votes)?;
+
+    apply_user_votes(deps.storage, &user, power, votes)?;
+
+    let event = Event::new("erishub/voting_power_locked")
+        .add_attribute("user", user)
+        .add_attribute("amount", amount)
+        .add_attribute("power", power);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/lock_voting_power"))
+}
+
+/// Return locked Stake token to the user, reducing their voting power and keeping their votes
+pub fn unlock_voting_power(deps: DepsMut, user: Addr, amount: Uint128) -> ContractResult<Response> {
+    let state = State::default();
+    let stake_token = state.stake_token.load(deps.storage)?;
+
+    let user_votes = load_user_votes(deps.storage, &user)?;
+    if amount.is_zero() || amount > user_votes.power {
+        return Err(ContractError::UnlockAmountTooHigh {
+            requested: amount,
+            locked: user_votes.power,
+        });
+    }
+
+    let power = user_votes.power - amount;
+    let votes = counted_votes(deps.storage, &user, user_votes.votes)?;
+
+    apply_user_votes(deps.storage, &user, power, votes)?;
+
+    let transfer_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: stake_token.into(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: user.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    });
+
+    let event = Event::new("erishub/voting_power_unlocked")
+        .add_attribute("user", user)
+        .add_attribute("amount", amount)
+        .add_attribute("power", power);
+
+    Ok(Response::new()
+        .add_message(transfer_msg)
+        .add_event(event)
+        .add_attribute("action", "erishub/unlock_voting_power"))
+}
+
+pub fn tally_votes(deps: DepsMut, env: Env) -> ContractResult<Response> {
+    let state = State::default();
+    let current_time = env.block.time.seconds();
+
+    let epoch_period = state.epoch_period.load(deps.storage)?;
+    let last_tally_time = state.gauge_last_tally_time.may_load(deps.storage)?.unwrap_or(0);
+    if current_time < last_tally_time + epoch_period {
+        return Err(ContractError::TallyNotReady(last_tally_time + epoch_period));
+    }
+
+    // Only votes for validators that are still whitelisted are taken into account
+    let validator_votes = query_validator_votes(deps.storage)?;
+    let total_votes: Uint128 = validator_votes.iter().map(|(_, votes)| *votes).sum();
+
+    let weights = if total_votes.is_zero() {
+        vec![]
+    } else {
+        validator_votes
+            .into_iter()
+            .map(|(validator, votes)| {
+                let weight = votes.multiply_ratio(MAX_BPS, total_votes).u128() as u64;
+                (validator, weight)
+            })
+            .collect()
+    };
+
+    state.gauge_weights.save(deps.storage, &weights)?;
+    state.gauge_last_tally_time.save(deps.storage, &current_time)?;
+
+    let event = Event::new("erishub/votes_tallied")
+        .add_attribute("time", current_time.to_string())
+        .add_attribute("total_votes", total_votes)
+        .add_attributes(weights.iter().map(|(validator, weight)| (validator, weight.to_string())));
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/tally_votes"))
+}
+
+/// Replace a user's votes, keeping the validators' vote totals consistent
+fn apply_user_votes(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    power: Uint128,
+    votes: Vec<(String, u16)>,
+) -> StdResult<()> {
+    let state = State::default();
+
+    if let Some(previous) = state.gauge_user_votes.may_load(storage, user)? {
+        for (validator, bps) in &previous.votes {
+            // Votes for a validator that has since been removed were discarded with its total
+            let counted = is_vote_counted(storage, user, validator)?;
+            state.gauge_user_vote_removals.remove(storage, (user, validator));
+            if !counted {
+                continue;
+            }
+
+            let amount = previous.power.multiply_ratio(*bps, MAX_BPS);
+            state.gauge_validator_votes.update(storage, validator, |total| -> StdResult<_> {
+                Ok(total.unwrap_or_default().checked_sub(amount)?)
+            })?;
+        }
+    }
+
+    for (validator, bps) in &votes {
+        if let Some(removals) = state.gauge_validator_removals.may_load(storage, validator)? {
+            state.gauge_user_vote_removals.save(storage, (user, validator), &removals)?;
+        }
+
+        let amount = power.multiply_ratio(*bps, MAX_BPS);
+        state.gauge_validator_votes.update(storage, validator, |total| -> StdResult<_> {
+            Ok(total.unwrap_or_default().checked_add(amount)?)
+        })?;
+    }
+
+    if power.is_zero() && votes.is_empty() {
+        state.gauge_user_votes.remove(storage, user);
+    } else {
+        state.gauge_user_votes.save(
+            storage,
+            user,
+            &UserVotes {
+                user: user.clone(),
+                power,
+                votes,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Discard the votes received by validators removed from the whitelist. Should they be added back,
+/// only votes cast after their removal count towards their total
+pub(crate) fn remove_validator_votes(
+    storage: &mut dyn Storage,
+    validators: &[String],
+) -> StdResult<()> {
+    let state = State::default();
+
+    for validator in validators {
+        state.gauge_validator_votes.remove(storage, validator);
+        state.gauge_validator_removals.update(storage, validator, |removals| -> StdResult<_> {
+            Ok(removals.unwrap_or(0) + 1)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Whether a user's vote for a validator still counts towards its total, i.e. the validator hasn't
+/// been removed from the whitelist since the vote was cast
+fn is_vote_counted(storage: &dyn Storage, user: &Addr, validator: &str) -> StdResult<bool> {
+    let state = State::default();
+    let removals = state.gauge_validator_removals.may_load(storage, validator)?;
+    let vote_removals = state.gauge_user_vote_removals.may_load(storage, (user, validator))?;
+    Ok(removals == vote_removals)
+}
+
+/// The user's votes that still count, to be carried over when their voting power changes
+fn counted_votes(
+    storage: &dyn Storage,
+    user: &Addr,
+    votes: Vec<(String, u16)>,
+) -> StdResult<Vec<(String, u16)>> {
+    let mut counted = vec![];
+    for (validator, bps) in votes {
+        if is_vote_counted(storage, user, &validator)? {
+            counted.push((validator, bps));
+        }
+    }
+    Ok(counted)
+}
+
+fn load_user_votes(storage: &dyn Storage, user: &Addr) -> StdResult<UserVotes> {
+    Ok(State::default().gauge_user_votes.may_load(storage, user)?.unwrap_or(UserVotes {
+        user: user.clone(),
+        power: Uint128::zero(),
+        votes: vec![],
+    }))
+}
+
+/// Total votes received by each of the whitelisted validators
+fn query_validator_votes(storage: &dyn Storage) -> StdResult<Vec<(String, Uint128)>> {
+    let state = State::default();
+
+    state
+        .validators
+        .load(storage)?
+        .into_iter()
+        .map(|validator| {
+            let votes =
+                state.gauge_validator_votes.may_load(storage, &validator)?.unwrap_or_default();
+            Ok((validator, votes))
+        })
+        .collect()
+}
+
+//--------------------------------------------------------------------------------------------------
+// Queries
+//--------------------------------------------------------------------------------------------------
+
+pub fn user_votes(deps: Deps, user: String) -> StdResult<UserVotes> {
+    let user = deps.api.addr_validate(&user)?;
+    load_user_votes(deps.storage, &user)
+}
+
+pub fn validator_votes(deps: Deps) -> StdResult<ValidatorVotesResponse> {
+    let state = State::default();
+
+    Ok(ValidatorVotesResponse {
+        last_tally_time: state.gauge_last_tally_time.may_load(deps.storage)?.unwrap_or(0),
+        votes: query_validator_votes(deps.storage)?,
+        tallied_weights: state.gauge_weights.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
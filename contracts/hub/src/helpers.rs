@@ -1,15 +1,16 @@
 use std::{collections::HashSet, str::FromStr};
 
 use cosmwasm_std::{
-    Addr, Api, Coin, QuerierWrapper, Reply, StdError, StdResult, SubMsgResponse, Uint128,
+    Addr, Api, Coin, QuerierWrapper, Reply, StdResult, SubMsgResponse, Uint128, Validator,
 };
 use cw20::{Cw20QueryMsg, TokenInfoResponse};
 
+use crate::error::{ContractError, ContractResult};
 use crate::types::Delegation;
 
 /// Unwrap a `Reply` object to extract the response
-pub(crate) fn unwrap_reply(reply: Reply) -> StdResult<SubMsgResponse> {
-    reply.result.into_result().map_err(StdError::generic_err)
+pub(crate) fn unwrap_reply(reply: Reply) -> ContractResult<SubMsgResponse> {
+    reply.result.into_result().map_err(ContractError::SubMsgFailed)
 }
 
 /// Query the total supply of a CW20 token
@@ -37,6 +38,15 @@ pub(crate) fn query_delegation(
     })
 }
 
+/// Query a validator, if it is in the active set. The `Validator` staking query returns any validator
+/// that exists, including jailed and tombstoned ones, so the active set is queried instead
+pub(crate) fn query_active_validator(
+    querier: &QuerierWrapper,
+    validator: &str,
+) -> StdResult<Option<Validator>> {
+    Ok(querier.query_all_validators()?.into_iter().find(|v| v.address == validator))
+}
+
 /// Query the amounts of Token a staker is delegating to each of the validators specified
 pub(crate) fn query_delegations(
     querier: &QuerierWrapper,
@@ -59,8 +69,8 @@ pub(crate) fn query_delegations(
 /// character that is not a number. Split the string at that index.
 ///
 /// This assumes the denom never starts with a number, which is true on Terra.
-pub(crate) fn parse_coin(s: &str) -> StdResult<Coin> {
-    for (i, c) in s.chars().enumerate() {
+pub(crate) fn parse_coin(s: &str) -> ContractResult<Coin> {
+    for (i, c) in s.char_indices() {
         if c.is_alphabetic() {
             let amount = Uint128::from_str(&s[..i])?;
             let denom = &s[i..];
@@ -68,29 +78,26 @@ pub(crate) fn parse_coin(s: &str) -> StdResult<Coin> {
         }
     }
 
-    Err(StdError::generic_err(format!("failed to parse coin: {}", s)))
+    Err(ContractError::InvalidCoin(s.to_string()))
 }
 
 /// Find the amount of a denom sent along a message, assert it is non-zero, and no other denom were
 /// sent together
-pub(crate) fn parse_received_fund(funds: &[Coin], denom: &str) -> StdResult<Uint128> {
+pub(crate) fn parse_received_fund(funds: &[Coin], denom: &str) -> ContractResult<Uint128> {
     if funds.len() != 1 {
-        return Err(StdError::generic_err(format!(
-            "must deposit exactly one coin; received {}",
-            funds.len()
-        )));
+        return Err(ContractError::InvalidFundsCount(funds.len()));
     }
 
     let fund = &funds[0];
     if fund.denom != denom {
-        return Err(StdError::generic_err(format!(
-            "expected {} deposit, received {}",
-            denom, fund.denom
-        )));
+        return Err(ContractError::InvalidDenom {
+            expected: denom.to_string(),
+            received: fund.denom.clone(),
+        });
     }
 
     if fund.amount.is_zero() {
-        return Err(StdError::generic_err("deposit amount must be non-zero"));
+        return Err(ContractError::ZeroDeposit);
     }
 
     Ok(fund.amount)
@@ -108,9 +115,9 @@ pub fn dedupe(v: &mut Vec<String>) {
 /// * **api** is an object of type [`Api`]
 ///
 /// * **addr** is an object of type [`Addr`]
-pub fn addr_validate_to_lower(api: &dyn Api, addr: &str) -> StdResult<Addr> {
+pub fn addr_validate_to_lower(api: &dyn Api, addr: &str) -> ContractResult<Addr> {
     if addr.to_lowercase() != addr {
-        return Err(StdError::generic_err(format!("Address {} should be lowercase", addr)));
+        return Err(ContractError::NonLowercaseAddress(addr.to_string()));
     }
-    api.addr_validate(addr)
+    Ok(api.addr_validate(addr)?)
 }
@@ -1,12 +1,17 @@
 #[cfg(not(feature = "library"))]
 pub mod contract;
 
+pub mod error;
 pub mod execute;
+pub mod gauges;
 pub mod helpers;
 pub mod math;
 pub mod queries;
 pub mod state;
+pub mod strategy;
+pub mod timelock;
 pub mod types;
+pub mod unbond_nft;
 
 #[cfg(test)]
 mod testing;
@@ -1,9 +1,11 @@
-use std::{cmp, cmp::Ordering};
+use std::cmp;
 
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Addr, Decimal, StdResult, Uint128};
 
-use eris_staking::hub::Batch;
+use eris_staking::hub::{Batch, DepositFeeSource, ExchangeRateSnapshot, FeeConfig};
+use eris_staking::DecimalCheckedOps;
 
+use crate::constants::SECONDS_PER_YEAR;
 use crate::types::{Delegation, Redelegation, Undelegation};
 
 //--------------------------------------------------------------------------------------------------
@@ -13,12 +15,17 @@ use crate::types::{Delegation, Redelegation, Undelegation};
 /// Compute the amount of Stake token to mint for a specific Token stake amount. If current total
 /// staked amount is zero, we use 1 ustake = 1 utoken; otherwise, we calculate base on the current
 /// utoken per ustake ratio.
+///
+/// Token held in the liquidity buffer belongs to the stakers just like the delegated Token, so it
+/// is part of the total staked amount.
 pub(crate) fn compute_mint_amount(
     ustake_supply: Uint128,
     utoken_to_bond: Uint128,
     current_delegations: &[Delegation],
+    utoken_buffered: Uint128,
 ) -> Uint128 {
-    let utoken_bonded: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    let utoken_bonded: u128 =
+        current_delegations.iter().map(|d| d.amount).sum::<u128>() + utoken_buffered.u128();
     if utoken_bonded == 0 {
         utoken_to_bond
     } else {
@@ -34,47 +41,238 @@ pub(crate) fn compute_unbond_amount(
     ustake_supply: Uint128,
     ustake_to_burn: Uint128,
     current_delegations: &[Delegation],
+    utoken_buffered: Uint128,
 ) -> Uint128 {
-    let utoken_bonded: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    let utoken_bonded: u128 =
+        current_delegations.iter().map(|d| d.amount).sum::<u128>() + utoken_buffered.u128();
     Uint128::new(utoken_bonded).multiply_ratio(ustake_to_burn, ustake_supply)
 }
 
+/// Amounts involved in bonding Token
+#[derive(Debug, PartialEq)]
+pub(crate) struct BondAmounts {
+    /// Deposit fee taken from the Token deposited
+    pub utoken_deposit_fee: Uint128,
+    /// Token staked, i.e. delegated or kept in the liquidity buffer
+    pub utoken_to_stake: Uint128,
+    /// Stake token minted in total, including the deposit fee taken from it
+    pub ustake_to_mint: Uint128,
+    /// Deposit fee taken from the Stake token minted
+    pub ustake_deposit_fee: Uint128,
+    /// Stake token minted to the receiver
+    pub ustake_to_receiver: Uint128,
+}
+
+/// Compute the amounts involved in bonding Token. Donations mint no Stake token and are not charged
+/// the deposit fee. Otherwise, depending on its source, the fee is taken either from the deposit,
+/// before the amount to mint is computed, or from the Stake token minted
+pub(crate) fn compute_bond_amounts(
+    fee_config: &FeeConfig,
+    utoken_to_bond: Uint128,
+    donate: bool,
+    ustake_supply: Uint128,
+    current_delegations: &[Delegation],
+    utoken_buffered: Uint128,
+) -> StdResult<BondAmounts> {
+    let deposit_fee = if donate {
+        Decimal::zero()
+    } else {
+        fee_config.deposit_fee
+    };
+
+    let utoken_deposit_fee = match fee_config.deposit_fee_source {
+        DepositFeeSource::Deposit => deposit_fee.checked_mul_uint(utoken_to_bond)?,
+        DepositFeeSource::Mint => Uint128::zero(),
+    };
+    let utoken_to_stake = utoken_to_bond - utoken_deposit_fee;
+
+    let ustake_to_mint = if donate {
+        Uint128::zero()
+    } else {
+        compute_mint_amount(ustake_supply, utoken_to_stake, current_delegations, utoken_buffered)
+    };
+    let ustake_deposit_fee = match fee_config.deposit_fee_source {
+        DepositFeeSource::Deposit => Uint128::zero(),
+        DepositFeeSource::Mint => deposit_fee.checked_mul_uint(ustake_to_mint)?,
+    };
+
+    Ok(BondAmounts {
+        utoken_deposit_fee,
+        utoken_to_stake,
+        ustake_to_mint,
+        ustake_deposit_fee,
+        ustake_to_receiver: ustake_to_mint - ustake_deposit_fee,
+    })
+}
+
+/// Split an amount of `utoken` being withdrawn into the amount received and the withdrawal fee
+pub(crate) fn deduct_withdrawal_fee(
+    fee_config: &FeeConfig,
+    utoken: Uint128,
+) -> StdResult<(Uint128, Uint128)> {
+    let utoken_withdrawal_fee = fee_config.withdrawal_fee.checked_mul_uint(utoken)?;
+    Ok((utoken - utoken_withdrawal_fee, utoken_withdrawal_fee))
+}
+
+/// Compute the amount of `utoken` per `ustake`. If there is no `ustake`, 1 ustake = 1 utoken
+pub(crate) fn compute_exchange_rate(total_utoken: Uint128, total_ustake: Uint128) -> Decimal {
+    if total_ustake.is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(total_utoken, total_ustake)
+    }
+}
+
+/// Compute the growth of the exchange rate between two snapshots, annualized. A decrease, e.g. after
+/// slashing, counts as zero. Returns `None` if the snapshots were not taken at different times
+pub(crate) fn compute_apr(
+    start: &ExchangeRateSnapshot,
+    end: &ExchangeRateSnapshot,
+) -> Option<Decimal> {
+    if end.time <= start.time || start.exchange_rate.is_zero() {
+        return None;
+    }
+    if end.exchange_rate <= start.exchange_rate {
+        return Some(Decimal::zero());
+    }
+
+    let growth = Decimal::from_ratio(
+        end.exchange_rate.atomics() - start.exchange_rate.atomics(),
+        start.exchange_rate.atomics(),
+    );
+    Some(growth * Decimal::from_ratio(SECONDS_PER_YEAR, end.time - start.time))
+}
+
+/// Compute the amount of `utoken` to keep in the liquidity buffer out of an incoming amount: the
+/// configured share of it, without filling the buffer beyond its target
+pub(crate) fn compute_buffer_refill(
+    utoken_incoming: Uint128,
+    share: Decimal,
+    utoken_buffered: Uint128,
+    target: Uint128,
+) -> StdResult<Uint128> {
+    let utoken_to_buffer = share.checked_mul_uint(utoken_incoming)?;
+    Ok(cmp::min(utoken_to_buffer, target.saturating_sub(utoken_buffered)))
+}
+
+/// Split a protocol fee between its recipients according to their shares. Each share is rounded
+/// down, and the last recipient receives the remainder so that the full fee is distributed.
+/// Recipients whose share rounds to zero are skipped.
+pub(crate) fn split_protocol_fee(
+    fee: Uint128,
+    recipients: &[(Addr, Decimal)],
+) -> Vec<(Addr, Uint128)> {
+    let mut fee_remaining = fee;
+
+    recipients
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (address, share))| {
+            let amount = if i + 1 == recipients.len() {
+                fee_remaining
+            } else {
+                fee * *share
+            };
+            fee_remaining -= amount;
+
+            if amount.is_zero() {
+                None
+            } else {
+                Some((address.clone(), amount))
+            }
+        })
+        .collect()
+}
+
 //--------------------------------------------------------------------------------------------------
 // Delegation logics
 //--------------------------------------------------------------------------------------------------
 
+/// Given a total amount of `utoken` and the target weight of each validator, compute the amount
+/// each validator should ideally have delegated to it.
+///
+/// Each validator receives `utoken_total * weight / total_weight`, rounded down. The remainder is
+/// handed out one `utoken` at a time to the validators with a non-zero weight, in order. If all
+/// weights are zero, every validator is treated as having the same weight.
+pub(crate) fn compute_target_delegations(utoken_total: u128, weights: &[u64]) -> Vec<u128> {
+    let weight_total: u128 = weights.iter().map(|w| *w as u128).sum();
+    if weight_total == 0 {
+        return compute_target_delegations(utoken_total, &vec![1; weights.len()]);
+    }
+
+    let mut targets = weights
+        .iter()
+        .map(|w| Uint128::new(utoken_total).multiply_ratio(*w, weight_total).u128())
+        .collect::<Vec<_>>();
+
+    let mut remainder = utoken_total - targets.iter().sum::<u128>();
+    for (target, weight) in targets.iter_mut().zip(weights) {
+        if remainder == 0 {
+            break;
+        }
+        if *weight > 0 {
+            *target += 1;
+            remainder -= 1;
+        }
+    }
+
+    targets
+}
+
+/// Find the validator whose delegation is the smallest relative to its target weight, i.e. the one
+/// that should receive new delegations. Validators with a zero weight are skipped, unless all of
+/// them have a zero weight, in which case the one with the smallest delegation is picked.
+///
+/// The code for linear search is a bit uglier than using `sort_by` but cheaper: O(n) vs O(n * log(n))
+pub(crate) fn find_validator_to_delegate<'a>(
+    current_delegations: &'a [Delegation],
+    weights: &[u64],
+) -> &'a Delegation {
+    if weights.iter().all(|w| *w == 0) {
+        return find_validator_to_delegate(current_delegations, &vec![1; weights.len()]);
+    }
+
+    let mut index: Option<usize> = None;
+    for (i, (d, weight)) in current_delegations.iter().zip(weights).enumerate() {
+        if *weight == 0 {
+            continue;
+        }
+        // d.amount / weight < best.amount / best_weight, cross-multiplied to avoid rounding
+        let is_smaller = match index {
+            None => true,
+            Some(j) => {
+                Uint128::new(d.amount).full_mul(weights[j])
+                    < Uint128::new(current_delegations[j].amount).full_mul(*weight)
+            },
+        };
+        if is_smaller {
+            index = Some(i);
+        }
+    }
+
+    &current_delegations[index.unwrap_or(0)]
+}
+
 /// Given the current delegations made to validators, and a specific amount of `utoken` to unstake,
-/// compute the undelegations to make such that the delegated amount to each validator is as even
-/// as possible.
+/// compute the undelegations to make such that the delegated amount to each validator is as close
+/// as possible to its target weight.
 ///
 /// This function is based on Lido's implementation:
 /// https://github.com/lidofinance/lido-terra-contracts/blob/v1.0.2/contracts/lido_terra_validators_registry/src/common.rs#L55-102
 pub(crate) fn compute_undelegations(
     utoken_to_unbond: Uint128,
     current_delegations: &[Delegation],
+    weights: &[u64],
 ) -> Vec<Undelegation> {
     let utoken_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
-    let validator_count = current_delegations.len() as u128;
 
     let utoken_to_distribute = utoken_staked - utoken_to_unbond.u128();
-    let utoken_per_validator = utoken_to_distribute / validator_count;
-    let remainder = utoken_to_distribute % validator_count;
+    let targets = compute_target_delegations(utoken_to_distribute, weights);
 
     let mut new_undelegations: Vec<Undelegation> = vec![];
     let mut utoken_available = utoken_to_unbond.u128();
-    for (i, d) in current_delegations.iter().enumerate() {
-        let remainder_for_validator: u128 = if (i + 1) as u128 <= remainder {
-            1
-        } else {
-            0
-        };
-        let utoken_for_validator = utoken_per_validator + remainder_for_validator;
-
-        let mut utoken_to_undelegate = if d.amount < utoken_for_validator {
-            0
-        } else {
-            d.amount - utoken_for_validator
-        };
+    for (d, utoken_for_validator) in current_delegations.iter().zip(targets) {
+        let mut utoken_to_undelegate = d.amount.saturating_sub(utoken_for_validator);
 
         utoken_to_undelegate = std::cmp::min(utoken_to_undelegate, utoken_available);
         utoken_available -= utoken_to_undelegate;
@@ -93,36 +291,24 @@ pub(crate) fn compute_undelegations(
 
 /// Given a validator who is to be removed from the whitelist, and current delegations made to other
 /// validators, compute the new delegations to make such that the delegated amount to each validator
-// is as even as possible.
+/// is as close as possible to its target weight.
 ///
 /// This function is based on Lido's implementation:
 /// https://github.com/lidofinance/lido-terra-contracts/blob/v1.0.2/contracts/lido_terra_validators_registry/src/common.rs#L19-L53
 pub(crate) fn compute_redelegations_for_removal(
     delegation_to_remove: &Delegation,
     current_delegations: &[Delegation],
+    weights: &[u64],
 ) -> Vec<Redelegation> {
     let utoken_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
-    let validator_count = current_delegations.len() as u128;
 
     let utoken_to_distribute = utoken_staked + delegation_to_remove.amount;
-    let utoken_per_validator = utoken_to_distribute / validator_count;
-    let remainder = utoken_to_distribute % validator_count;
+    let targets = compute_target_delegations(utoken_to_distribute, weights);
 
     let mut new_redelegations: Vec<Redelegation> = vec![];
     let mut utoken_available = delegation_to_remove.amount;
-    for (i, d) in current_delegations.iter().enumerate() {
-        let remainder_for_validator: u128 = if (i + 1) as u128 <= remainder {
-            1
-        } else {
-            0
-        };
-        let utoken_for_validator = utoken_per_validator + remainder_for_validator;
-
-        let mut utoken_to_redelegate = if d.amount > utoken_for_validator {
-            0
-        } else {
-            utoken_for_validator - d.amount
-        };
+    for (d, utoken_for_validator) in current_delegations.iter().zip(targets) {
+        let mut utoken_to_redelegate = utoken_for_validator.saturating_sub(d.amount);
 
         utoken_to_redelegate = std::cmp::min(utoken_to_redelegate, utoken_available);
         utoken_available -= utoken_to_redelegate;
@@ -146,69 +332,177 @@ pub(crate) fn compute_redelegations_for_removal(
 /// Compute redelegation moves that will make each validator's delegation the targeted amount (hopefully
 /// this sentence makes sense)
 ///
-/// This algorithm does not guarantee the minimal number of moves, but is the best I can some up with...
+/// Validators in `blocked_validators` can not be redelegated from. Whatever they hold above their
+/// target is left in place, and the validators below their target are topped up only with what the
+/// other validators can give. Validators that deviate from their target by less than
+/// `min_deviation` are left as they are, and no move smaller than `min_redelegation` is made.
+///
+/// The moves are planned with `group_deviations`, so that the number of moves is as small as
+/// possible.
 pub(crate) fn compute_redelegations_for_rebalancing(
     current_delegations: &[Delegation],
+    weights: &[u64],
+    blocked_validators: &[String],
+    min_deviation: u128,
+    min_redelegation: u128,
 ) -> Vec<Redelegation> {
     let utoken_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
-    let validator_count = current_delegations.len() as u128;
-
-    let utoken_per_validator = utoken_staked / validator_count;
-    let remainder = utoken_staked % validator_count;
-
-    // If a validator's current delegated amount is greater than the target amount, Token will be
-    // redelegated _from_ them. They will be put in `src_validators` vector
-    // If a validator's current delegated amount is smaller than the target amount, Token will be
-    // redelegated _to_ them. They will be put in `dst_validators` vector
-    let mut src_delegations: Vec<Delegation> = vec![];
-    let mut dst_delegations: Vec<Delegation> = vec![];
-    for (i, d) in current_delegations.iter().enumerate() {
-        let remainder_for_validator: u128 = if (i + 1) as u128 <= remainder {
-            1
-        } else {
-            0
-        };
-        let utoken_for_validator = utoken_per_validator + remainder_for_validator;
-
-        match d.amount.cmp(&utoken_for_validator) {
-            Ordering::Greater => {
-                src_delegations
-                    .push(Delegation::new(&d.validator, d.amount - utoken_for_validator));
-            },
-            Ordering::Less => {
-                dst_delegations
-                    .push(Delegation::new(&d.validator, utoken_for_validator - d.amount));
-            },
-            Ordering::Equal => (),
+    let targets = compute_target_delegations(utoken_staked, weights);
+
+    // If a validator's current delegated amount is greater than the target amount, its deviation is
+    // positive and Token will be redelegated _from_ them.
+    // If a validator's current delegated amount is smaller than the target amount, its deviation is
+    // negative and Token will be redelegated _to_ them.
+    let mut validators: Vec<Option<&str>> = vec![];
+    let mut deviations: Vec<i128> = vec![];
+    for (d, utoken_for_validator) in current_delegations.iter().zip(targets) {
+        let deviation = d.amount as i128 - utoken_for_validator as i128;
+        if deviation == 0 || deviation.unsigned_abs() < min_deviation {
+            continue;
         }
+        if deviation > 0 && blocked_validators.contains(&d.validator) {
+            continue;
+        }
+        validators.push(Some(&d.validator));
+        deviations.push(deviation);
+    }
+
+    // If the deviations don't cancel out, e.g. because a blocked validator keeps its excess, a
+    // placeholder takes the difference. Moves from or to the placeholder are not made
+    let imbalance: i128 = deviations.iter().sum();
+    if imbalance != 0 {
+        validators.push(None);
+        deviations.push(-imbalance);
     }
 
     let mut new_redelegations: Vec<Redelegation> = vec![];
-    while !src_delegations.is_empty() && !dst_delegations.is_empty() {
-        let src_delegation = src_delegations[0].clone();
-        let dst_delegation = dst_delegations[0].clone();
-        let utoken_to_redelegate = cmp::min(src_delegation.amount, dst_delegation.amount);
+    for group in group_deviations(&deviations) {
+        let mut src_delegations: Vec<(Option<&str>, u128)> = vec![];
+        let mut dst_delegations: Vec<(Option<&str>, u128)> = vec![];
+        for i in group {
+            if deviations[i] > 0 {
+                src_delegations.push((validators[i], deviations[i].unsigned_abs()));
+            } else {
+                dst_delegations.push((validators[i], deviations[i].unsigned_abs()));
+            }
+        }
 
-        if src_delegation.amount == utoken_to_redelegate {
-            src_delegations.remove(0);
-        } else {
-            src_delegations[0].amount -= utoken_to_redelegate;
+        // Within a group, pairing sources with destinations one after the other finishes off at
+        // least one of them with each move, and both of them with the last move
+        let (mut i, mut j) = (0, 0);
+        while i < src_delegations.len() && j < dst_delegations.len() {
+            let (src, src_amount) = src_delegations[i];
+            let (dst, dst_amount) = dst_delegations[j];
+            let utoken_to_redelegate = cmp::min(src_amount, dst_amount);
+
+            src_delegations[i].1 -= utoken_to_redelegate;
+            if src_delegations[i].1 == 0 {
+                i += 1;
+            }
+            dst_delegations[j].1 -= utoken_to_redelegate;
+            if dst_delegations[j].1 == 0 {
+                j += 1;
+            }
+
+            if let (Some(src), Some(dst)) = (src, dst) {
+                if utoken_to_redelegate >= min_redelegation {
+                    new_redelegations.push(Redelegation::new(src, dst, utoken_to_redelegate));
+                }
+            }
+        }
+    }
+
+    new_redelegations
+}
+
+/// Deviations up to which `group_deviations` searches all of their subsets
+const MAX_DEVIATIONS_FOR_EXACT_GROUPING: usize = 12;
+
+/// Split deviations that add up to zero into as many groups that also add up to zero as possible,
+/// returning the indexes of the deviations in each group. A group of `n` validators can be settled
+/// with `n - 1` moves, so the more groups, the fewer moves in total.
+///
+/// Finding the best split requires going through all subsets of the deviations, which is only done
+/// when there are few of them. Otherwise, deviations that cancel each other out are paired, and the
+/// rest are put in a single group.
+fn group_deviations(deviations: &[i128]) -> Vec<Vec<usize>> {
+    let n = deviations.len();
+    if n == 0 {
+        return vec![];
+    }
+    if n > MAX_DEVIATIONS_FOR_EXACT_GROUPING {
+        return pair_deviations(deviations);
+    }
+
+    // Adding the deviations one at a time, the number of groups is the number of times the running
+    // sum is zero. For each subset, find the order of its deviations that makes this the most often
+    let size = 1usize << n;
+    let mut sums = vec![0i128; size];
+    let mut group_counts = vec![0usize; size];
+    let mut last_added = vec![0usize; size];
+    for mask in 1..size {
+        sums[mask] = sums[mask & (mask - 1)] + deviations[mask.trailing_zeros() as usize];
+
+        let mut best: Option<(usize, usize)> = None;
+        for (i, _) in deviations.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0) {
+            let count = group_counts[mask ^ (1 << i)];
+            if best.map(|(best_count, _)| count > best_count).unwrap_or(true) {
+                best = Some((count, i));
+            }
         }
+        let (count, i) = best.unwrap_or_default();
 
-        if dst_delegation.amount == utoken_to_redelegate {
-            dst_delegations.remove(0);
-        } else {
-            dst_delegations[0].amount -= utoken_to_redelegate;
+        group_counts[mask] = count + usize::from(sums[mask] == 0);
+        last_added[mask] = i;
+    }
+
+    let mut order: Vec<usize> = vec![];
+    let mut mask = size - 1;
+    while mask != 0 {
+        order.push(last_added[mask]);
+        mask ^= 1 << last_added[mask];
+    }
+    order.reverse();
+
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut group: Vec<usize> = vec![];
+    let mut sum: i128 = 0;
+    for i in order {
+        group.push(i);
+        sum += deviations[i];
+        if sum == 0 {
+            group.sort_unstable();
+            groups.push(std::mem::take(&mut group));
         }
+    }
+
+    groups.sort();
+    groups
+}
 
-        new_redelegations.push(Redelegation::new(
-            &src_delegation.validator,
-            &dst_delegation.validator,
-            utoken_to_redelegate,
-        ));
+/// Pair deviations that cancel each other out, and put the rest in a single group
+fn pair_deviations(deviations: &[i128]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut paired = vec![false; deviations.len()];
+    for i in 0..deviations.len() {
+        if paired[i] {
+            continue;
+        }
+        let counterpart =
+            (i + 1..deviations.len()).find(|j| !paired[*j] && deviations[*j] == -deviations[i]);
+        if let Some(j) = counterpart {
+            paired[i] = true;
+            paired[j] = true;
+            groups.push(vec![i, j]);
+        }
     }
 
-    new_redelegations
+    let rest = (0..deviations.len()).filter(|i| !paired[*i]).collect::<Vec<_>>();
+    if !rest.is_empty() {
+        groups.push(rest);
+    }
+
+    groups
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -241,7 +535,18 @@ pub(crate) fn reconcile_batches(batches: &mut [Batch], utoken_to_deduct: Uint128
 
 /// If all funds are available we still need to mark batches as reconciled
 pub(crate) fn mark_reconciled_batches(batches: &mut [Batch]) {
-    for (_, batch) in batches.iter_mut().enumerate() {
+    for batch in batches.iter_mut() {
         batch.reconciled = true;
     }
 }
+
+/// Compute the amount of `utoken` refunded for an unbond request's shares in a batch, and deduct
+/// both from the batch
+pub(crate) fn withdraw_from_batch(batch: &mut Batch, shares: Uint128) -> Uint128 {
+    let utoken_to_refund = batch.utoken_unclaimed.multiply_ratio(shares, batch.total_shares);
+
+    batch.total_shares -= shares;
+    batch.utoken_unclaimed -= utoken_to_refund;
+
+    utoken_to_refund
+}
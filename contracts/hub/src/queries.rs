@@ -2,19 +2,30 @@ use cosmwasm_std::{Addr, Decimal, Deps, Env, Order, StdResult, Uint128};
 use cw_storage_plus::Bound;
 
 use eris_staking::hub::{
-    Batch, ConfigResponse, PendingBatch, StateResponse, UnbondRequestsByBatchResponseItem,
-    UnbondRequestsByUserResponseItem, UnbondRequestsByUserResponseItemDetails,
+    AprResponse, Batch, ConfigResponse, DelegationResponseItem, ExchangeRateSnapshot, PendingBatch,
+    RedelegationResponseItem, Role, SimulateBondResponse, SimulateUnbondResponse, StateResponse,
+    SwapRoute, UnbondRequestsByBatchResponseItem, UnbondRequestsByUserResponseItem,
+    UnbondRequestsByUserResponseItemDetails, WithdrawableResponse,
 };
 
 use crate::constants::CONTRACT_DENOM;
+use crate::error::ContractError;
+use crate::execute::compute_reconciliation;
 use crate::helpers::{query_cw20_total_supply, query_delegations};
+use crate::math::{
+    compute_apr, compute_bond_amounts, compute_exchange_rate, compute_target_delegations,
+    compute_unbond_amount, deduct_withdrawal_fee, withdraw_from_batch,
+};
 use crate::state::State;
+use crate::strategy::{load_strategy, plan_rebalancing};
+use crate::unbond_nft::withdrawable_unbond_requests;
 
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+pub(crate) const MAX_LIMIT: u32 = 30;
+pub(crate) const DEFAULT_LIMIT: u32 = 10;
 
 pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
     let state = State::default();
+    let validators = state.validators.load(deps.storage)?;
 
     Ok(ConfigResponse {
         owner: state.owner.load(deps.storage)?.into(),
@@ -22,8 +33,15 @@ pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
         stake_token: state.stake_token.load(deps.storage)?.into(),
         epoch_period: state.epoch_period.load(deps.storage)?,
         unbond_period: state.unbond_period.load(deps.storage)?,
-        validators: state.validators.load(deps.storage)?,
+        validator_weights: state.load_validator_weights(deps.storage, &validators)?,
+        validators,
         fee_config: state.fee_config.load(deps.storage)?,
+        delegation_strategy: state.load_delegation_strategy(deps.storage)?,
+        max_commission: state.load_max_commission(deps.storage)?,
+        min_redelegation: state.min_redelegation.may_load(deps.storage)?.unwrap_or_default(),
+        liquidity_buffer: state.buffer_config.may_load(deps.storage)?.unwrap_or_default(),
+        timelock_delay: state.timelock_delay.may_load(deps.storage)?.unwrap_or(0),
+        paused: state.pause_state.may_load(deps.storage)?.unwrap_or_default(),
     })
 }
 
@@ -36,6 +54,8 @@ pub fn state(deps: Deps, env: Env) -> StdResult<StateResponse> {
     let validators = state.validators.load(deps.storage)?;
     let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
     let total_utoken: u128 = delegations.iter().map(|d| d.amount).sum();
+    let buffer_utoken = state.buffer.may_load(deps.storage)?.unwrap_or_default();
+    let buffer_config = state.buffer_config.may_load(deps.storage)?.unwrap_or_default();
 
     // only not reconciled batches are relevant as they are still unbonding and estimated unbond time in the future.
     let unbonding: u128 = state
@@ -54,11 +74,9 @@ pub fn state(deps: Deps, env: Env) -> StdResult<StateResponse> {
 
     let available = deps.querier.query_balance(&env.contract.address, CONTRACT_DENOM)?.amount;
 
-    let exchange_rate = if total_ustake.is_zero() {
-        Decimal::one()
-    } else {
-        Decimal::from_ratio(total_utoken, total_ustake)
-    };
+    // Token held in the liquidity buffer belongs to the stakers as well
+    let exchange_rate =
+        compute_exchange_rate(Uint128::new(total_utoken) + buffer_utoken, total_ustake);
 
     Ok(StateResponse {
         total_ustake,
@@ -70,6 +88,8 @@ pub fn state(deps: Deps, env: Env) -> StdResult<StateResponse> {
         tvl_utoken: Uint128::from(total_utoken)
             .checked_add(Uint128::from(unbonding))?
             .checked_add(available)?,
+        buffer_utoken,
+        buffer_target: buffer_config.target,
     })
 }
 
@@ -217,3 +237,272 @@ pub fn unbond_requests_by_user_details(
         })
         .collect()
 }
+
+pub fn simulate_rebalance(
+    deps: Deps,
+    env: Env,
+    min_deviation: Option<Uint128>,
+    max_redelegations: Option<u32>,
+    force: Option<bool>,
+) -> StdResult<Vec<RedelegationResponseItem>> {
+    Ok(plan_rebalancing(deps, &env, min_deviation, max_redelegations, force.unwrap_or(false))?
+        .into_iter()
+        .map(|rd| RedelegationResponseItem {
+            src: rd.src,
+            dst: rd.dst,
+            amount: Uint128::new(rd.amount),
+        })
+        .collect())
+}
+
+pub fn delegations(deps: Deps, env: Env) -> StdResult<Vec<DelegationResponseItem>> {
+    let state = State::default();
+    let validators = state.validators.load(deps.storage)?;
+
+    // The amount delegated and the rewards accumulated come from the same query
+    let delegations = validators
+        .iter()
+        .map(|validator| {
+            let delegation = deps.querier.query_delegation(&env.contract.address, validator)?;
+            Ok(match delegation {
+                Some(fd) => (fd.amount.amount, fd.accumulated_rewards),
+                None => (Uint128::zero(), vec![]),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let weights = load_strategy(deps)?.weights(deps, &validators)?;
+
+    let total_amount: Uint128 = delegations.iter().map(|(amount, _)| *amount).sum();
+
+    // The targets the hub rebalances towards, so that all-zero weights fall back to an even split
+    let targets = compute_target_delegations(total_amount.u128(), &weights);
+
+    Ok(validators
+        .into_iter()
+        .zip(delegations)
+        .zip(targets)
+        .map(|((validator, (amount, pending_rewards)), target)| {
+            let (share, target_share) = if total_amount.is_zero() {
+                (Decimal::zero(), Decimal::zero())
+            } else {
+                (
+                    Decimal::from_ratio(amount, total_amount),
+                    Decimal::from_ratio(target, total_amount),
+                )
+            };
+
+            DelegationResponseItem {
+                validator,
+                amount,
+                share,
+                target_share,
+                pending_rewards,
+            }
+        })
+        .collect())
+}
+
+pub fn withdrawable(deps: Deps, env: Env, user: String) -> StdResult<WithdrawableResponse> {
+    let state = State::default();
+    let user = deps.api.addr_validate(&user)?;
+
+    // Like `ExecuteMsg::WithdrawUnbonded`, batches that have finished unbonding are reconciled first.
+    // They are then updated in memory as each request is withdrawn, in the same order, so that the
+    // amount matches exactly
+    let (mut batches, _) = compute_reconciliation(deps, &env)?;
+
+    let requests = withdrawable_unbond_requests(
+        deps.storage,
+        &user,
+        env.block.time.seconds(),
+        &batches,
+        None,
+        None,
+    )?;
+
+    let mut batch_ids = vec![];
+    let mut utoken = Uint128::zero();
+    for request in requests {
+        let batch = match batches.iter_mut().find(|batch| batch.id == request.id) {
+            Some(batch) => batch,
+            None => {
+                batches.push(state.previous_batches.load(deps.storage, request.id)?);
+                batches.last_mut().unwrap()
+            },
+        };
+
+        utoken += withdraw_from_batch(batch, request.shares);
+        batch_ids.push(request.id);
+    }
+
+    batch_ids.dedup();
+
+    // The amount reported is the one received, after the withdrawal fee
+    let fee_config = state.fee_config.load(deps.storage)?;
+    let (utoken, _) = deduct_withdrawal_fee(&fee_config, utoken)?;
+
+    Ok(WithdrawableResponse {
+        batch_ids,
+        utoken,
+    })
+}
+
+pub fn swap_routes(deps: Deps) -> StdResult<Vec<SwapRoute>> {
+    let state = State::default();
+
+    state
+        .swap_routes
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+pub fn role_holders(
+    deps: Deps,
+    role: Role,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<String>> {
+    let state = State::default();
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after.map(|s| deps.api.addr_validate(&s)).transpose()?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    state
+        .roles
+        .prefix(role.as_str())
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(String::from))
+        .collect()
+}
+
+/// Mirrors `execute::bond`. The part of the deposit kept in the liquidity buffer does not change
+/// the amount minted, since it is counted as bonded either way
+pub fn simulate_bond(deps: Deps, env: Env, amount: Uint128) -> StdResult<SimulateBondResponse> {
+    let state = State::default();
+    let stake_token = state.stake_token.load(deps.storage)?;
+    let validators = state.validators.load(deps.storage)?;
+    let fee_config = state.fee_config.load(deps.storage)?;
+
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
+    let ustake_supply = query_cw20_total_supply(&deps.querier, &stake_token)?;
+    let utoken_buffered = state.buffer.may_load(deps.storage)?.unwrap_or_default();
+
+    let amounts = compute_bond_amounts(
+        &fee_config,
+        amount,
+        false,
+        ustake_supply,
+        &delegations,
+        utoken_buffered,
+    )?;
+
+    Ok(SimulateBondResponse {
+        ustake_to_receive: amounts.ustake_to_receiver,
+        utoken_deposit_fee: amounts.utoken_deposit_fee,
+        ustake_deposit_fee: amounts.ustake_deposit_fee,
+    })
+}
+
+/// The amount is computed at the current exchange rate; the actual one is set when the batch is
+/// submitted, and may be higher thanks to the rewards reinvested in between
+pub fn simulate_unbond(deps: Deps, env: Env, ustake: Uint128) -> StdResult<SimulateUnbondResponse> {
+    let state = State::default();
+    let stake_token = state.stake_token.load(deps.storage)?;
+    let validators = state.validators.load(deps.storage)?;
+    let unbond_period = state.unbond_period.load(deps.storage)?;
+    let pending_batch = state.pending_batch.load(deps.storage)?;
+    let fee_config = state.fee_config.load(deps.storage)?;
+
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
+    let ustake_supply = query_cw20_total_supply(&deps.querier, &stake_token)?;
+    let utoken_buffered = state.buffer.may_load(deps.storage)?.unwrap_or_default();
+
+    if ustake > ustake_supply {
+        return Err(ContractError::ExceedsTotalSupply {
+            ustake,
+            ustake_supply,
+        }
+        .into());
+    }
+
+    let utoken_to_unbond = if ustake.is_zero() {
+        Uint128::zero()
+    } else {
+        compute_unbond_amount(ustake_supply, ustake, &delegations, utoken_buffered)
+    };
+    let (utoken_to_receive, utoken_withdrawal_fee) =
+        deduct_withdrawal_fee(&fee_config, utoken_to_unbond)?;
+
+    // A batch that is overdue can be submitted right away
+    let est_unbond_start_time = pending_batch.est_unbond_start_time.max(env.block.time.seconds());
+
+    Ok(SimulateUnbondResponse {
+        utoken_to_receive,
+        utoken_withdrawal_fee,
+        batch_id: pending_batch.id,
+        est_unbond_start_time,
+        est_unbond_end_time: est_unbond_start_time + unbond_period,
+    })
+}
+
+pub fn exchange_rates(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ExchangeRateSnapshot>> {
+    let state = State::default();
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    state
+        .exchange_rates
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+pub fn apr(deps: Deps, window: Option<u64>) -> StdResult<AprResponse> {
+    let state = State::default();
+
+    let end = state
+        .exchange_rates
+        .range(deps.storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map(|(_, snapshot)| snapshot);
+
+    let start = match &end {
+        Some(end) => {
+            let min = window.map(|window| Bound::inclusive(end.time.saturating_sub(window)));
+            state
+                .exchange_rates
+                .range(deps.storage, min, None, Order::Ascending)
+                .next()
+                .transpose()?
+                .map(|(_, snapshot)| snapshot)
+        },
+        None => None,
+    };
+
+    let apr = match (&start, &end) {
+        (Some(start), Some(end)) => compute_apr(start, end),
+        _ => None,
+    };
+
+    Ok(AprResponse {
+        apr,
+        start,
+        end,
+    })
+}
@@ -1,9 +1,15 @@
-use cosmwasm_std::{Addr, Coin, StdError, StdResult, Storage};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use cosmwasm_std::{Addr, Coin, Decimal, Empty, Order, StdResult, Storage, Uint128};
+use cw20::Expiration;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
-use eris_staking::hub::{Batch, FeeConfig, PendingBatch, UnbondRequest};
+use eris_staking::hub::{
+    Batch, DelegationStrategyConfig, ExchangeRateSnapshot, FeeConfig, LiquidityBufferConfig,
+    PauseState, PendingBatch, PendingChange, Role, SwapRoute, UnbondRequest, UserVotes,
+};
 
-use crate::types::BooleanKey;
+use crate::constants::{DEFAULT_VALIDATOR_WEIGHT, EXCHANGE_RATE_HISTORY_SIZE};
+use crate::error::{ContractError, ContractResult};
+use crate::types::{BooleanKey, Redelegation};
 
 pub(crate) struct State<'a> {
     /// Account who can call certain privileged functions
@@ -18,6 +24,8 @@ pub(crate) struct State<'a> {
     pub unbond_period: Item<'a, u64>,
     /// Validators who will receive the delegations
     pub validators: Item<'a, Vec<String>>,
+    /// Target weight of each whitelisted validator, relative to the other validators' weights
+    pub validator_weights: Map<'a, &'a str, u64>,
     /// Coins that can be reinvested
     pub unlocked_coins: Item<'a, Vec<Coin>>,
     /// The current batch of unbonding requests queded to be executed
@@ -26,8 +34,64 @@ pub(crate) struct State<'a> {
     pub previous_batches: IndexedMap<'a, u64, Batch, PreviousBatchesIndexes<'a>>,
     /// Users' shares in unbonding batches
     pub unbond_requests: IndexedMap<'a, (u64, &'a Addr), UnbondRequest, UnbondRequestsIndexes<'a>>,
+    /// Unbond requests whose Token is sent to their holder as soon as their batch has finished
+    /// unbonding, by `ExecuteMsg::DistributeUnbonded`
+    pub auto_claims: Map<'a, (u64, &'a Addr), Empty>,
+    /// Accounts holding unbond requests that were transferred to them. Requests without an entry
+    /// are held by the user who queued them
+    pub unbond_request_holders:
+        IndexedMap<'a, (u64, &'a Addr), Addr, UnbondRequestHoldersIndexes<'a>>,
+    /// Accounts approved to transfer an unbond request, keyed by the request and the spender
+    pub unbond_request_approvals: Map<'a, (u64, &'a Addr, &'a Addr), Expiration>,
+    /// Operators allowed to transfer all unbond requests held by an account, keyed by the holder
+    /// and the operator
+    pub unbond_request_operators: Map<'a, (&'a Addr, &'a Addr), Expiration>,
+    /// Number of unbond requests in existence
+    pub unbond_request_count: Item<'a, u64>,
     /// Fee Config
     pub fee_config: Item<'a, FeeConfig>,
+    /// Strategy used to allocate delegations between validators
+    pub delegation_strategy: Item<'a, DelegationStrategyConfig>,
+    /// Maximum commission rate a whitelisted validator may charge
+    pub max_commission: Item<'a, Decimal>,
+    /// Smallest amount of `utoken` moved by a redelegation when rebalancing
+    pub min_redelegation: Item<'a, Uint128>,
+    /// Routes used to swap reward coins into Token during harvest, keyed by denom
+    pub swap_routes: Map<'a, &'a str, SwapRoute>,
+    /// Size, fee and refill rules of the liquidity buffer
+    pub buffer_config: Item<'a, LiquidityBufferConfig>,
+    /// Amount of `utoken` held in the liquidity buffer
+    pub buffer: Item<'a, Uint128>,
+    /// Completion time of the latest redelegation the hub has made to each validator. Until then,
+    /// the staking module rejects redelegations out of that validator
+    pub redelegation_completion_times: Map<'a, &'a str, u64>,
+    /// Gauge votes cast by each user
+    pub gauge_user_votes: Map<'a, &'a Addr, UserVotes>,
+    /// Total gauge votes received by each validator
+    pub gauge_validator_votes: Map<'a, &'a str, Uint128>,
+    /// Number of times each validator has been removed from the whitelist. Votes for a validator
+    /// only count towards its total if they were cast since its last removal
+    pub gauge_validator_removals: Map<'a, &'a str, u32>,
+    /// Number of removals of the validator at the time a user's vote for it was counted
+    pub gauge_user_vote_removals: Map<'a, (&'a Addr, &'a str), u32>,
+    /// Target weights of the validators resulting from the last tally of the gauge votes
+    pub gauge_weights: Item<'a, Vec<(String, u64)>>,
+    /// Time of the last tally of the gauge votes, in seconds
+    pub gauge_last_tally_time: Item<'a, u64>,
+    /// Delay after which the changes proposed by the owner can be executed, in seconds
+    pub timelock_delay: Item<'a, u64>,
+    /// Changes proposed by the owner that are yet to be executed, keyed by ID
+    pub pending_changes: Map<'a, u64, PendingChange>,
+    /// ID to assign to the next proposed change
+    pub next_pending_change_id: Item<'a, u64>,
+    /// Accounts holding each role, keyed by role and account
+    pub roles: Map<'a, (&'a str, &'a Addr), Empty>,
+    /// Operations currently paused
+    pub pause_state: Item<'a, PauseState>,
+    /// Most recent exchange rate snapshots, keyed by time
+    pub exchange_rates: Map<'a, u64, ExchangeRateSnapshot>,
+    /// Number of exchange rate snapshots stored
+    pub exchange_rate_count: Item<'a, u64>,
 }
 
 impl Default for State<'static> {
@@ -46,6 +110,13 @@ impl Default for State<'static> {
                 "unbond_requests__user",
             ),
         };
+        let urh_indexes = UnbondRequestHoldersIndexes {
+            holder: MultiIndex::new(
+                |d: &Addr| d.to_string(),
+                "unbond_request_holders",
+                "unbond_request_holders__holder",
+            ),
+        };
         Self {
             owner: Item::new("owner"),
             new_owner: Item::new("new_owner"),
@@ -53,24 +124,161 @@ impl Default for State<'static> {
             epoch_period: Item::new("epoch_period"),
             unbond_period: Item::new("unbond_period"),
             validators: Item::new("validators"),
+            validator_weights: Map::new("validator_weights"),
             unlocked_coins: Item::new("unlocked_coins"),
             pending_batch: Item::new("pending_batch"),
             previous_batches: IndexedMap::new("previous_batches", pb_indexes),
             unbond_requests: IndexedMap::new("unbond_requests", ubr_indexes),
+            unbond_request_holders: IndexedMap::new("unbond_request_holders", urh_indexes),
+            unbond_request_approvals: Map::new("unbond_request_approvals"),
+            unbond_request_operators: Map::new("unbond_request_operators"),
+            unbond_request_count: Item::new("unbond_request_count"),
+            auto_claims: Map::new("auto_claims"),
             fee_config: Item::new("fee_config"),
+            delegation_strategy: Item::new("delegation_strategy"),
+            max_commission: Item::new("max_commission"),
+            min_redelegation: Item::new("min_redelegation"),
+            swap_routes: Map::new("swap_routes"),
+            buffer_config: Item::new("buffer_config"),
+            buffer: Item::new("buffer"),
+            redelegation_completion_times: Map::new("redelegation_completion_times"),
+            gauge_user_votes: Map::new("gauge_user_votes"),
+            gauge_validator_votes: Map::new("gauge_validator_votes"),
+            gauge_validator_removals: Map::new("gauge_validator_removals"),
+            gauge_user_vote_removals: Map::new("gauge_user_vote_removals"),
+            gauge_weights: Item::new("gauge_weights"),
+            gauge_last_tally_time: Item::new("gauge_last_tally_time"),
+            timelock_delay: Item::new("timelock_delay"),
+            pending_changes: Map::new("pending_changes"),
+            next_pending_change_id: Item::new("next_pending_change_id"),
+            roles: Map::new("roles"),
+            pause_state: Item::new("pause_state"),
+            exchange_rates: Map::new("exchange_rates"),
+            exchange_rate_count: Item::new("exchange_rate_count"),
         }
     }
 }
 
 impl<'a> State<'a> {
-    pub fn assert_owner(&self, storage: &dyn Storage, sender: &Addr) -> StdResult<()> {
+    pub fn assert_owner(&self, storage: &dyn Storage, sender: &Addr) -> ContractResult<()> {
         let owner = self.owner.load(storage)?;
         if *sender == owner {
             Ok(())
         } else {
-            Err(StdError::generic_err("unauthorized: sender is not owner"))
+            Err(ContractError::Unauthorized)
+        }
+    }
+
+    pub fn has_role(&self, storage: &dyn Storage, account: &Addr, role: Role) -> StdResult<bool> {
+        Ok(self.roles.may_load(storage, (role.as_str(), account))?.is_some())
+    }
+
+    /// The owner can perform the duties of every role
+    pub fn assert_owner_or_role(
+        &self,
+        storage: &dyn Storage,
+        sender: &Addr,
+        role: Role,
+    ) -> ContractResult<()> {
+        let owner = self.owner.load(storage)?;
+        if *sender == owner || self.has_role(storage, sender, role)? {
+            Ok(())
+        } else {
+            Err(ContractError::UnauthorizedRole(role))
         }
     }
+
+    /// Load the delegation strategy config. Contracts that never stored one use weighted delegations
+    pub fn load_delegation_strategy(
+        &self,
+        storage: &dyn Storage,
+    ) -> StdResult<DelegationStrategyConfig> {
+        Ok(self
+            .delegation_strategy
+            .may_load(storage)?
+            .unwrap_or(DelegationStrategyConfig::Weighted))
+    }
+
+    /// Load the maximum validator commission. Contracts that never stored one have no cap
+    pub fn load_max_commission(&self, storage: &dyn Storage) -> StdResult<Decimal> {
+        Ok(self.max_commission.may_load(storage)?.unwrap_or_else(Decimal::one))
+    }
+
+    /// Record an exchange rate snapshot, dropping the oldest one once the history is full. A snapshot
+    /// taken at the same time as the latest one replaces it
+    pub fn save_exchange_rate(
+        &self,
+        storage: &mut dyn Storage,
+        snapshot: &ExchangeRateSnapshot,
+    ) -> StdResult<()> {
+        if self.exchange_rates.has(storage, snapshot.time) {
+            return self.exchange_rates.save(storage, snapshot.time, snapshot);
+        }
+
+        self.exchange_rates.save(storage, snapshot.time, snapshot)?;
+
+        let count = self.exchange_rate_count.may_load(storage)?.unwrap_or(0) + 1;
+        if count > EXCHANGE_RATE_HISTORY_SIZE {
+            let oldest = self.exchange_rates.keys(storage, None, None, Order::Ascending).next();
+            if let Some(oldest) = oldest {
+                self.exchange_rates.remove(storage, oldest?);
+            }
+            self.exchange_rate_count.save(storage, &EXCHANGE_RATE_HISTORY_SIZE)
+        } else {
+            self.exchange_rate_count.save(storage, &count)
+        }
+    }
+
+    /// Among the validators specified, find those that can not be redelegated from at the current
+    /// time, because a redelegation the hub has made to them has not completed yet
+    pub fn load_blocked_validators(
+        &self,
+        storage: &dyn Storage,
+        validators: &[String],
+        current_time: u64,
+    ) -> StdResult<Vec<String>> {
+        let mut blocked_validators: Vec<String> = vec![];
+        for validator in validators {
+            let completion_time =
+                self.redelegation_completion_times.may_load(storage, validator)?.unwrap_or(0);
+            if completion_time > current_time {
+                blocked_validators.push(validator.clone());
+            }
+        }
+        Ok(blocked_validators)
+    }
+
+    /// Record the completion time of redelegations made by the hub, so that their destinations are
+    /// not used as redelegation sources until then
+    pub fn save_redelegations(
+        &self,
+        storage: &mut dyn Storage,
+        redelegations: &[Redelegation],
+        completion_time: u64,
+    ) -> StdResult<()> {
+        for rd in redelegations {
+            self.redelegation_completion_times.save(storage, &rd.dst, &completion_time)?;
+        }
+        Ok(())
+    }
+
+    /// Load the target weight of each of the validators specified, in the same order. Validators
+    /// without a stored weight use the default weight.
+    pub fn load_validator_weights(
+        &self,
+        storage: &dyn Storage,
+        validators: &[String],
+    ) -> StdResult<Vec<u64>> {
+        validators
+            .iter()
+            .map(|validator| {
+                Ok(self
+                    .validator_weights
+                    .may_load(storage, validator)?
+                    .unwrap_or(DEFAULT_VALIDATOR_WEIGHT))
+            })
+            .collect()
+    }
 }
 
 pub(crate) struct PreviousBatchesIndexes<'a> {
@@ -97,3 +305,16 @@ impl<'a> IndexList<UnbondRequest> for UnbondRequestsIndexes<'a> {
         Box::new(v.into_iter())
     }
 }
+
+pub(crate) struct UnbondRequestHoldersIndexes<'a> {
+    // pk goes to second tuple element
+    pub holder: MultiIndex<'a, String, Addr, (u64, &'a Addr)>,
+}
+
+impl<'a> IndexList<Addr> for UnbondRequestHoldersIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Addr>> + '_> {
+        let v: Vec<&dyn Index<Addr>> = vec![&self.holder];
+
+        Box::new(v.into_iter())
+    }
+}
@@ -0,0 +1,209 @@
+use cosmwasm_std::{Addr, Api, Deps, Env, StdResult, Uint128};
+
+use eris_staking::hub::{DelegationStrategyConfig, StrategyQueryMsg, ValidatorWeightsResponse};
+
+use crate::helpers::query_delegations;
+use crate::math::{
+    compute_redelegations_for_rebalancing, compute_redelegations_for_removal,
+    compute_undelegations, find_validator_to_delegate,
+};
+use crate::state::State;
+use crate::types::{Delegation, Redelegation, Undelegation};
+
+/// Decides how delegations are allocated between the whitelisted validators.
+///
+/// Implementations only need to provide the target weight of each validator; bonding, unbonding,
+/// removal and rebalancing all converge on those weights through the functions in `math`.
+pub(crate) trait DelegationStrategy {
+    /// Target weight of each of the validators specified, in the same order
+    fn weights(&self, deps: Deps, validators: &[String]) -> StdResult<Vec<u64>>;
+
+    /// Find the validator that should receive new delegations
+    fn validator_to_delegate<'a>(
+        &self,
+        deps: Deps,
+        current_delegations: &'a [Delegation],
+    ) -> StdResult<&'a Delegation> {
+        let weights = self.weights(deps, &validators_of(current_delegations))?;
+        Ok(find_validator_to_delegate(current_delegations, &weights))
+    }
+
+    /// Compute the undelegations to make to unbond a specific amount of `utoken`
+    fn undelegations(
+        &self,
+        deps: Deps,
+        utoken_to_unbond: Uint128,
+        current_delegations: &[Delegation],
+    ) -> StdResult<Vec<Undelegation>> {
+        let weights = self.weights(deps, &validators_of(current_delegations))?;
+        Ok(compute_undelegations(utoken_to_unbond, current_delegations, &weights))
+    }
+
+    /// Compute the redelegations to make when a validator is removed from the whitelist
+    fn redelegations_for_removal(
+        &self,
+        deps: Deps,
+        delegation_to_remove: &Delegation,
+        current_delegations: &[Delegation],
+    ) -> StdResult<Vec<Redelegation>> {
+        let weights = self.weights(deps, &validators_of(current_delegations))?;
+        Ok(compute_redelegations_for_removal(delegation_to_remove, current_delegations, &weights))
+    }
+
+    /// Compute the redelegations to make to bring every validator deviating from its target
+    /// delegation by at least `min_deviation` to it, without redelegating out of the blocked
+    /// validators or moving less than `min_redelegation`
+    fn redelegations_for_rebalancing(
+        &self,
+        deps: Deps,
+        current_delegations: &[Delegation],
+        blocked_validators: &[String],
+        min_deviation: u128,
+        min_redelegation: u128,
+    ) -> StdResult<Vec<Redelegation>> {
+        let weights = self.weights(deps, &validators_of(current_delegations))?;
+        Ok(compute_redelegations_for_rebalancing(
+            current_delegations,
+            &weights,
+            blocked_validators,
+            min_deviation,
+            min_redelegation,
+        ))
+    }
+}
+
+/// Every validator has the same weight
+pub(crate) struct Uniform;
+
+impl DelegationStrategy for Uniform {
+    fn weights(&self, _deps: Deps, validators: &[String]) -> StdResult<Vec<u64>> {
+        Ok(vec![1; validators.len()])
+    }
+}
+
+/// Validators use the weights configured by the owner
+pub(crate) struct Weighted;
+
+impl DelegationStrategy for Weighted {
+    fn weights(&self, deps: Deps, validators: &[String]) -> StdResult<Vec<u64>> {
+        State::default().load_validator_weights(deps.storage, validators)
+    }
+}
+
+/// Validators use the weights provided by an external contract
+pub(crate) struct External {
+    pub contract: Addr,
+}
+
+impl DelegationStrategy for External {
+    fn weights(&self, deps: Deps, validators: &[String]) -> StdResult<Vec<u64>> {
+        let res: ValidatorWeightsResponse = deps.querier.query_wasm_smart(
+            &self.contract,
+            &StrategyQueryMsg::ValidatorWeights {
+                validators: validators.to_vec(),
+            },
+        )?;
+
+        Ok(validators
+            .iter()
+            .map(|validator| {
+                res.weights.iter().find(|(v, _)| v == validator).map(|(_, w)| *w).unwrap_or(0)
+            })
+            .collect())
+    }
+}
+
+/// Validators use the weights resulting from the last tally of the gauge votes
+pub(crate) struct Gauges;
+
+impl DelegationStrategy for Gauges {
+    fn weights(&self, deps: Deps, validators: &[String]) -> StdResult<Vec<u64>> {
+        let tallied_weights =
+            State::default().gauge_weights.may_load(deps.storage)?.unwrap_or_default();
+
+        Ok(validators
+            .iter()
+            .map(|validator| {
+                tallied_weights.iter().find(|(v, _)| v == validator).map(|(_, w)| *w).unwrap_or(0)
+            })
+            .collect())
+    }
+}
+
+/// Instantiate the strategy described by the config stored in the contract
+pub(crate) fn load_strategy(deps: Deps) -> StdResult<Box<dyn DelegationStrategy>> {
+    let config = State::default().load_delegation_strategy(deps.storage)?;
+
+    Ok(match config {
+        DelegationStrategyConfig::Uniform => Box::new(Uniform),
+        DelegationStrategyConfig::Weighted => Box::new(Weighted),
+        DelegationStrategyConfig::External {
+            contract,
+        } => Box::new(External {
+            contract: Addr::unchecked(contract),
+        }),
+        DelegationStrategyConfig::Gauges => Box::new(Gauges),
+    })
+}
+
+/// Plan the redelegations to make to rebalance the delegations. Validators deviating from their
+/// target by less than `min_deviation` are left as they are, and no single move smaller than the
+/// configured `min_redelegation` is made, unless `force` is set, which lifts that minimum. If there
+/// are more than `max_redelegations` moves, only the largest are kept.
+pub(crate) fn plan_rebalancing(
+    deps: Deps,
+    env: &Env,
+    min_deviation: Option<Uint128>,
+    max_redelegations: Option<u32>,
+    force: bool,
+) -> StdResult<Vec<Redelegation>> {
+    let state = State::default();
+    let validators = state.validators.load(deps.storage)?;
+
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
+
+    let blocked_validators =
+        state.load_blocked_validators(deps.storage, &validators, env.block.time.seconds())?;
+
+    // Forcing a rebalance lifts the configured minimum redelegation, e.g. to settle small
+    // deviations the regular rebalancing leaves in place
+    let min_redelegation = if force {
+        Uint128::zero()
+    } else {
+        state.min_redelegation.may_load(deps.storage)?.unwrap_or_default()
+    };
+
+    let mut new_redelegations = load_strategy(deps)?.redelegations_for_rebalancing(
+        deps,
+        &delegations,
+        &blocked_validators,
+        min_deviation.unwrap_or_default().u128(),
+        min_redelegation.u128(),
+    )?;
+
+    if let Some(max_redelegations) = max_redelegations {
+        new_redelegations.sort_by_key(|rd| std::cmp::Reverse(rd.amount));
+        new_redelegations.truncate(max_redelegations as usize);
+    }
+
+    Ok(new_redelegations)
+}
+
+/// Validate the addresses contained in a strategy config before it is saved
+pub(crate) fn validate_strategy_config(
+    api: &dyn Api,
+    config: DelegationStrategyConfig,
+) -> StdResult<DelegationStrategyConfig> {
+    Ok(match config {
+        DelegationStrategyConfig::External {
+            contract,
+        } => DelegationStrategyConfig::External {
+            contract: api.addr_validate(&contract)?.into(),
+        },
+        config => config,
+    })
+}
+
+fn validators_of(delegations: &[Delegation]) -> Vec<String> {
+    delegations.iter().map(|d| d.validator.clone()).collect()
+}
@@ -2,10 +2,14 @@ use std::collections::HashMap;
 
 use cosmwasm_std::testing::{BankQuerier, StakingQuerier, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, from_slice, Addr, Coin, Empty, FullDelegation, Querier, QuerierResult,
-    QueryRequest, SystemError, WasmQuery,
+    from_binary, from_slice, to_binary, Addr, Coin, ContractResult, Decimal, Empty, FullDelegation,
+    Querier, QuerierResult, QueryRequest, StakingQuery, SystemError, SystemResult, Validator,
+    ValidatorResponse, WasmQuery,
 };
 use cw20::Cw20QueryMsg;
+use eris_staking::hub::{
+    RouterQueryMsg, SimulateSwapOperationsResponse, StrategyQueryMsg, ValidatorWeightsResponse,
+};
 
 use crate::constants::CONTRACT_DENOM;
 use crate::types::Delegation;
@@ -18,6 +22,18 @@ pub(super) struct CustomQuerier {
     pub cw20_querier: Cw20Querier,
     pub bank_querier: BankQuerier,
     pub staking_querier: StakingQuerier,
+    pub staking_validators: Vec<Validator>,
+    /// Validators that exist but are not in the active set, e.g. because they are jailed. Like on
+    /// chain, they are returned by `StakingQuery::Validator` but not by `AllValidators`
+    pub jailed_validators: Vec<Validator>,
+    pub staking_delegations: Vec<FullDelegation>,
+    /// Weights returned by any contract queried with `StrategyQueryMsg::ValidatorWeights`
+    pub validator_weights: Vec<(String, u64)>,
+    /// Amount of Token received per coin offered to any contract queried with
+    /// `RouterQueryMsg::SimulateSwapOperations`
+    pub swap_rate: Decimal,
+    /// Routers whose `RouterQueryMsg::SimulateSwapOperations` query fails
+    pub failing_routers: Vec<String>,
 }
 
 impl Querier for CustomQuerier {
@@ -59,8 +75,39 @@ impl CustomQuerier {
         self.bank_querier = BankQuerier::new(&[(MOCK_CONTRACT_ADDR, balances)])
     }
 
+    /// Validators in the active set, with their commission rates
+    pub fn set_staking_validators(&mut self, validators: &[(&str, Decimal)]) {
+        self.staking_validators = validators
+            .iter()
+            .map(|(address, commission)| Validator {
+                address: address.to_string(),
+                commission: *commission,
+                max_commission: Decimal::one(),
+                max_change_rate: Decimal::one(),
+            })
+            .collect();
+
+        self.staking_querier = StakingQuerier::new(
+            CONTRACT_DENOM,
+            &self.staking_validators,
+            &self.staking_delegations,
+        );
+    }
+
+    pub fn set_jailed_validators(&mut self, validators: &[&str]) {
+        self.jailed_validators = validators
+            .iter()
+            .map(|address| Validator {
+                address: address.to_string(),
+                commission: Decimal::zero(),
+                max_commission: Decimal::one(),
+                max_change_rate: Decimal::one(),
+            })
+            .collect();
+    }
+
     pub fn set_staking_delegations(&mut self, delegations: &[Delegation]) {
-        let fds = delegations
+        self.staking_delegations = delegations
             .iter()
             .map(|d| FullDelegation {
                 delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
@@ -71,7 +118,23 @@ impl CustomQuerier {
             })
             .collect::<Vec<_>>();
 
-        self.staking_querier = StakingQuerier::new(CONTRACT_DENOM, &[], &fds);
+        self.staking_querier = StakingQuerier::new(
+            CONTRACT_DENOM,
+            &self.staking_validators,
+            &self.staking_delegations,
+        );
+    }
+
+    pub fn set_accumulated_rewards(&mut self, validator: &str, rewards: &[Coin]) {
+        for d in self.staking_delegations.iter_mut().filter(|d| d.validator == validator) {
+            d.accumulated_rewards = rewards.to_vec();
+        }
+
+        self.staking_querier = StakingQuerier::new(
+            CONTRACT_DENOM,
+            &self.staking_validators,
+            &self.staking_delegations,
+        );
     }
 
     pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
@@ -84,11 +147,55 @@ impl CustomQuerier {
                     return self.cw20_querier.handle_query(contract_addr, query);
                 }
 
+                if let Ok(StrategyQueryMsg::ValidatorWeights {
+                    ..
+                }) = from_binary::<StrategyQueryMsg>(msg)
+                {
+                    return Ok(to_binary(&ValidatorWeightsResponse {
+                        weights: self.validator_weights.clone(),
+                    })
+                    .into())
+                    .into();
+                }
+
+                if let Ok(RouterQueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    ..
+                }) = from_binary::<RouterQueryMsg>(msg)
+                {
+                    if self.failing_routers.contains(contract_addr) {
+                        return SystemResult::Ok(ContractResult::Err(
+                            "[mock] no route found".to_string(),
+                        ));
+                    }
+                    return Ok(to_binary(&SimulateSwapOperationsResponse {
+                        amount: offer_amount * self.swap_rate,
+                    })
+                    .into())
+                    .into();
+                }
+
                 err_unsupported_query(msg)
             },
 
             QueryRequest::Bank(query) => self.bank_querier.query(query),
 
+            QueryRequest::Staking(StakingQuery::Validator {
+                address,
+            }) => {
+                let validator = self
+                    .staking_validators
+                    .iter()
+                    .chain(self.jailed_validators.iter())
+                    .find(|v| v.address == *address)
+                    .cloned();
+                Ok(to_binary(&ValidatorResponse {
+                    validator,
+                })
+                .into())
+                .into()
+            },
+
             QueryRequest::Staking(query) => self.staking_querier.query(query),
 
             _ => err_unsupported_query(request),
@@ -1,13 +1,13 @@
-use cosmwasm_std::testing::{mock_env, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, Addr, BlockInfo, ContractInfo, Deps, Env, OwnedDeps, QuerierResult, SystemError,
-    SystemResult, Timestamp,
+    from_binary, Addr, BlockInfo, ContractInfo, Deps, DepsMut, Env, OwnedDeps, QuerierResult,
+    SystemError, SystemResult, Timestamp,
 };
 use serde::de::DeserializeOwned;
 
-use eris_staking::hub::QueryMsg;
+use eris_staking::hub::{ExecuteMsg, QueryMsg, UpdateConfigMsg};
 
-use crate::contract::query;
+use crate::contract::{execute, query};
 
 use super::custom_querier::CustomQuerier;
 
@@ -23,7 +23,7 @@ pub(super) fn mock_dependencies() -> OwnedDeps<MockStorage, MockApi, CustomQueri
         storage: MockStorage::default(),
         api: MockApi::default(),
         querier: CustomQuerier::default(),
-        custom_query_type: std::marker::PhantomData::default(),
+        custom_query_type: std::marker::PhantomData,
     }
 }
 
@@ -52,3 +52,7 @@ pub(super) fn query_helper_env<T: DeserializeOwned>(
 ) -> T {
     from_binary(&query(deps, mock_env_at_timestamp(timestamp), msg).unwrap()).unwrap()
 }
+
+pub(super) fn update_config(deps: DepsMut, msg: UpdateConfigMsg) {
+    execute(deps, mock_env(), mock_info("owner", &[]), ExecuteMsg::UpdateConfig(msg)).unwrap();
+}
@@ -1,4 +1,15 @@
 mod custom_querier;
 mod cw20_querier;
 mod helpers;
+mod tests_buffer;
 mod tests_default;
+mod tests_delegations;
+mod tests_exchange_rates;
+mod tests_fees;
+mod tests_gauges;
+mod tests_pause;
+mod tests_roles;
+mod tests_simulation;
+mod tests_swap;
+mod tests_timelock;
+mod tests_unbond_nft;
@@ -0,0 +1,389 @@
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    coin, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, DepsMut, SubMsg, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+
+use eris_staking::hub::{
+    Batch, CallbackMsg, ConfigResponse, ExecuteMsg, LiquidityBufferConfig, QueryMsg, ReceiveMsg,
+    StateResponse,
+};
+
+use crate::constants::CONTRACT_DENOM;
+use crate::contract::execute;
+use crate::error::ContractError;
+use crate::state::State;
+use crate::types::{Delegation, SendFee};
+
+use super::helpers::{mock_env_at_timestamp, query_helper};
+use super::tests_default::{check_received_coin, setup_test, STAKE_DENOM};
+
+fn buffer_config(target: u128) -> LiquidityBufferConfig {
+    LiquidityBufferConfig {
+        target: Uint128::new(target),
+        bond_share: Decimal::percent(20),
+        reward_share: Decimal::percent(50),
+        instant_unbond_fee: Decimal::percent(1),
+    }
+}
+
+fn update_buffer(deps: DepsMut, config: LiquidityBufferConfig) {
+    execute(
+        deps,
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateLiquidityBuffer {
+            config,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn updating_liquidity_buffer() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::UpdateLiquidityBuffer {
+            config: buffer_config(1000),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateLiquidityBuffer {
+            config: LiquidityBufferConfig {
+                bond_share: Decimal::percent(101),
+                ..buffer_config(1000)
+            },
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidBufferShares);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateLiquidityBuffer {
+            config: LiquidityBufferConfig {
+                instant_unbond_fee: Decimal::permille(51),
+                ..buffer_config(1000)
+            },
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::GreaterThanMax("instant_unbond_fee".to_string()));
+
+    update_buffer(deps.as_mut(), buffer_config(1000));
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.liquidity_buffer, buffer_config(1000));
+}
+
+#[test]
+fn bonding_into_buffer() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    update_buffer(deps.as_mut(), buffer_config(1000));
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 10000),
+        Delegation::new("bob", 10000),
+        Delegation::new("charlie", 10000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 30000);
+    deps.querier.set_bank_balances(&[coin(2000, CONTRACT_DENOM)]);
+
+    // 20% of the deposit is kept in the buffer: 2000 * 0.2 = 400
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(2000, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("alice", 1600).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "user_1".to_string(),
+                amount: Uint128::new(2000)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    // The buffered part of the deposit stays in the balance, and is not received coins
+    assert_eq!(res.messages[2], check_received_coin(400));
+
+    let buffer = state.buffer.load(deps.as_ref().storage).unwrap();
+    assert_eq!(buffer, Uint128::new(400));
+
+    // The buffer counts towards the exchange rate: 32000 ustake for 11600 + 20000 + 400 utoken
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 11600),
+        Delegation::new("bob", 10000),
+        Delegation::new("charlie", 10000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 32000);
+    deps.querier.set_bank_balances(&[coin(5400, CONTRACT_DENOM)]);
+
+    // 5000 * 0.2 = 1000, but the buffer only needs 1000 - 400 = 600 to reach its target
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_2", &[Coin::new(5000, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("bob", 4400).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "user_2".to_string(),
+                amount: Uint128::new(5000)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(res.messages[2], check_received_coin(1000));
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 11600),
+        Delegation::new("bob", 14400),
+        Delegation::new("charlie", 10000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 37000);
+    deps.querier.set_bank_balances(&[coin(1000, CONTRACT_DENOM)]);
+
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(res.total_utoken, Uint128::new(36000));
+    assert_eq!(res.exchange_rate, Decimal::one());
+    assert_eq!(res.buffer_utoken, Uint128::new(1000));
+    assert_eq!(res.buffer_target, Uint128::new(1000));
+}
+
+#[test]
+fn instant_unbonding() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    update_buffer(deps.as_mut(), buffer_config(1000));
+    state.buffer.save(deps.as_mut().storage, &Uint128::new(1000)).unwrap();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 3000),
+        Delegation::new("bob", 3000),
+        Delegation::new("charlie", 3000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 10000);
+
+    // Only Stake token can be redeemed
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("random_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(500),
+            msg: to_binary(&ReceiveMsg::InstantUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidStakeToken("random_token".to_string()));
+
+    // 500 ustake = (9000 + 1000) * 500 / 10000 = 500 utoken
+    // Fee: 500 * 0.01 = 5, which stays in the buffer
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(500),
+            msg: to_binary(&ReceiveMsg::InstantUnbond {
+                receiver: Some("user_2".to_string()),
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(500)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_2".to_string(),
+            amount: vec![Coin::new(495, CONTRACT_DENOM)]
+        }))
+    );
+
+    let buffer = state.buffer.load(deps.as_ref().storage).unwrap();
+    assert_eq!(buffer, Uint128::new(505));
+
+    // 600 ustake = (9000 + 505) * 600 / 9500 = 600 utoken, minus a fee of 6, is more than the
+    // buffer holds
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 9500);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(600),
+            msg: to_binary(&ReceiveMsg::InstantUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::InsufficientBuffer {
+            available: Uint128::new(505),
+            required: Uint128::new(594),
+        }
+    );
+}
+
+#[test]
+fn reinvesting_into_buffer() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    update_buffer(deps.as_mut(), buffer_config(1000));
+    state.buffer.save(deps.as_mut().storage, &Uint128::new(800)).unwrap();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334),
+        Delegation::new("bob", 333333),
+        Delegation::new("charlie", 333333),
+    ]);
+
+    // Protocol fee: 1000 * 0.01 = 10
+    // Buffer: (1000 - 10) * 0.5 = 495, but the buffer only needs 1000 - 800 = 200
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(1000, CONTRACT_DENOM)])
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("bob", 790).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(SendFee::new(Addr::unchecked("fee"), 10).to_cosmos_msg())
+    );
+
+    let buffer = state.buffer.load(deps.as_ref().storage).unwrap();
+    assert_eq!(buffer, Uint128::new(1000));
+
+    // After the target is lowered, the excess is delegated with the next rewards
+    update_buffer(deps.as_mut(), buffer_config(600));
+
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(100, CONTRACT_DENOM)])
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("bob", 99 + 400).to_cosmos_msg()));
+
+    let buffer = state.buffer.load(deps.as_ref().storage).unwrap();
+    assert_eq!(buffer, Uint128::new(600));
+}
+
+#[test]
+fn reconciling_with_buffer() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    state.buffer.save(deps.as_mut().storage, &Uint128::new(500)).unwrap();
+    state
+        .previous_batches
+        .save(
+            deps.as_mut().storage,
+            1,
+            &Batch {
+                id: 1,
+                reconciled: false,
+                total_shares: Uint128::new(1000),
+                utoken_unclaimed: Uint128::new(1000),
+                est_unbond_end_time: 10000,
+            },
+        )
+        .unwrap();
+
+    // The buffer is part of the balance, so only 1400 - 500 = 900 of the 1000 expected were received
+    deps.querier.set_bank_balances(&[coin(1400, CONTRACT_DENOM)]);
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+
+    let batch = state.previous_batches.load(deps.as_ref().storage, 1).unwrap();
+    assert!(batch.reconciled);
+    assert_eq!(batch.utoken_unclaimed, Uint128::new(900));
+}
@@ -3,24 +3,29 @@ use std::vec;
 
 use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    coin, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, DistributionMsg, Event, Order,
-    OwnedDeps, Reply, StdError, SubMsg, SubMsgResponse, Uint128, WasmMsg,
+    coin, from_slice, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut,
+    DistributionMsg, Event, Order, OwnedDeps, Reply, StdError, SubMsg, SubMsgResponse, Uint128,
+    WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, MinterResponse};
 use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
 use eris_staking::DecimalCheckedOps;
 
 use eris_staking::hub::{
-    Batch, CallbackMsg, ConfigResponse, ExecuteMsg, FeeConfig, InstantiateMsg, PendingBatch,
-    QueryMsg, ReceiveMsg, StateResponse, UnbondRequest, UnbondRequestsByBatchResponseItem,
-    UnbondRequestsByUserResponseItem, UnbondRequestsByUserResponseItemDetails,
+    Batch, CallbackMsg, ConfigResponse, DelegationStrategyConfig, DepositFeeSource, ExecuteMsg,
+    FeeConfig, InstantiateMsg, LiquidityBufferConfig, PauseState, PendingBatch, QueryMsg,
+    ReceiveMsg, RedelegationResponseItem, StateResponse, UnbondRequest,
+    UnbondRequestsByBatchResponseItem, UnbondRequestsByUserResponseItem,
+    UnbondRequestsByUserResponseItemDetails, UpdateConfigMsg, WithdrawableResponse,
 };
 
 use crate::constants::CONTRACT_DENOM;
 use crate::contract::{execute, instantiate, reply};
+use crate::error::ContractError;
 use crate::helpers::{dedupe, parse_coin, parse_received_fund};
 use crate::math::{
-    compute_redelegations_for_rebalancing, compute_redelegations_for_removal, compute_undelegations,
+    compute_redelegations_for_rebalancing, compute_redelegations_for_removal,
+    compute_target_delegations, compute_undelegations, find_validator_to_delegate,
 };
 use crate::state::State;
 use crate::testing::helpers::query_helper_env;
@@ -35,7 +40,7 @@ pub const STAKE_DENOM: &str = "stake_token";
 // Test setup
 //--------------------------------------------------------------------------------------------------
 
-fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
+pub(super) fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
     let mut deps = mock_dependencies();
 
     let res = instantiate(
@@ -53,6 +58,7 @@ fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
             validators: vec!["alice".to_string(), "bob".to_string(), "charlie".to_string()],
             protocol_fee_contract: "fee".to_string(),
             protocol_reward_fee: Decimal::from_ratio(1u128, 100u128),
+            delegation_strategy: None,
         },
     )
     .unwrap();
@@ -126,10 +132,20 @@ fn proper_instantiation() {
             epoch_period: 259200,
             unbond_period: 1814400,
             validators: vec!["alice".to_string(), "bob".to_string(), "charlie".to_string()],
+            validator_weights: vec![100, 100, 100],
             fee_config: FeeConfig {
-                protocol_fee_contract: Addr::unchecked("fee"),
-                protocol_reward_fee: Decimal::from_ratio(1u128, 100u128)
+                protocol_fee_recipients: vec![(Addr::unchecked("fee"), Decimal::one())],
+                protocol_reward_fee: Decimal::from_ratio(1u128, 100u128),
+                deposit_fee: Decimal::zero(),
+                deposit_fee_source: DepositFeeSource::Deposit,
+                withdrawal_fee: Decimal::zero(),
             },
+            delegation_strategy: DelegationStrategyConfig::Weighted,
+            max_commission: Decimal::one(),
+            min_redelegation: Uint128::zero(),
+            liquidity_buffer: LiquidityBufferConfig::default(),
+            timelock_delay: 0,
+            paused: PauseState::default(),
         }
     );
 
@@ -144,6 +160,8 @@ fn proper_instantiation() {
             unbonding: Uint128::zero(),
             available: Uint128::zero(),
             tvl_utoken: Uint128::zero(),
+            buffer_utoken: Uint128::zero(),
+            buffer_target: Uint128::zero(),
         },
     );
 
@@ -251,6 +269,8 @@ fn bonding() {
             unbonding: Uint128::zero(),
             available: Uint128::new(12567),
             tvl_utoken: Uint128::new(1037345 + 12567),
+            buffer_utoken: Uint128::zero(),
+            buffer_target: Uint128::zero(),
         }
     );
 }
@@ -309,6 +329,8 @@ fn donating() {
             unbonding: Uint128::zero(),
             available: Uint128::new(100),
             tvl_utoken: Uint128::new(1025100),
+            buffer_utoken: Uint128::zero(),
+            buffer_target: Uint128::zero(),
         }
     );
 
@@ -346,6 +368,8 @@ fn donating() {
             unbonding: Uint128::zero(),
             available: Uint128::new(100),
             tvl_utoken: Uint128::new(1037345 + 100),
+            buffer_utoken: Uint128::zero(),
+            buffer_target: Uint128::zero(),
         }
     );
 }
@@ -492,6 +516,57 @@ fn reinvesting() {
     );
 }
 
+#[test]
+fn reinvesting_with_split_protocol_fee() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334),
+        Delegation::new("bob", 333333),
+        Delegation::new("charlie", 333333),
+    ]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            protocol_fee_recipients: Some(vec![
+                ("treasury".to_string(), Decimal::percent(50)),
+                ("insurance".to_string(), Decimal::percent(30)),
+                ("incentives".to_string(), Decimal::percent(20)),
+            ]),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap();
+
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(123456, CONTRACT_DENOM)])
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    // The 1234 utoken fee is split 617 / 370 / 247, the last recipient receiving the remainder
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(Delegation::new("bob", 122222).to_cosmos_msg()),
+            SubMsg::new(SendFee::new(Addr::unchecked("treasury"), 617).to_cosmos_msg()),
+            SubMsg::new(SendFee::new(Addr::unchecked("insurance"), 370).to_cosmos_msg()),
+            SubMsg::new(SendFee::new(Addr::unchecked("incentives"), 247).to_cosmos_msg()),
+        ]
+    );
+}
+
 #[test]
 fn queuing_unbond() {
     let mut deps = setup_test();
@@ -507,13 +582,14 @@ fn queuing_unbond() {
             amount: Uint128::new(69420),
             msg: to_binary(&ReceiveMsg::QueueUnbond {
                 receiver: None,
+                auto_claim: None,
             })
             .unwrap(),
         }),
     )
     .unwrap_err();
 
-    assert_eq!(err, StdError::generic_err("expecting Stake token, received random_token"));
+    assert_eq!(err, ContractError::InvalidStakeToken("random_token".to_string()));
 
     // User 1 creates an unbonding request before `est_unbond_start_time` is reached. The unbond
     // request is saved, but not the pending batch is not submitted for unbonding
@@ -526,6 +602,7 @@ fn queuing_unbond() {
             amount: Uint128::new(23456),
             msg: to_binary(&ReceiveMsg::QueueUnbond {
                 receiver: None,
+                auto_claim: None,
             })
             .unwrap(),
         }),
@@ -545,6 +622,7 @@ fn queuing_unbond() {
             amount: Uint128::new(69420),
             msg: to_binary(&ReceiveMsg::QueueUnbond {
                 receiver: Some("user_3".to_string()),
+                auto_claim: None,
             })
             .unwrap(),
         }),
@@ -556,7 +634,10 @@ fn queuing_unbond() {
         res.messages[0],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: MOCK_CONTRACT_ADDR.to_string(),
-            msg: to_binary(&ExecuteMsg::SubmitBatch {}).unwrap(),
+            msg: to_binary(&ExecuteMsg::SubmitBatch {
+                force: None,
+            })
+            .unwrap(),
             funds: vec![]
         }))
     );
@@ -600,6 +681,169 @@ fn queuing_unbond() {
     );
 }
 
+#[test]
+fn cancelling_unbond() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    for (user, amount) in [("user_1", 23456), ("user_2", 69420)] {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(12345),
+            mock_info(STAKE_DENOM, &[]),
+            ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: user.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_binary(&ReceiveMsg::QueueUnbond {
+                    receiver: None,
+                    auto_claim: None,
+                })
+                .unwrap(),
+            }),
+        )
+        .unwrap();
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_3", &[]),
+        ExecuteMsg::CancelUnbond {
+            amount: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::UnbondRequestNotFound {
+            id: 1,
+            user: "user_3".to_string(),
+        }
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CancelUnbond {
+            amount: Some(Uint128::new(23457)),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::CancelAmountTooHigh {
+            requested: Uint128::new(23457),
+            available: Uint128::new(23456),
+        }
+    );
+
+    // User 1 takes back part of their request
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CancelUnbond {
+            amount: Some(Uint128::new(3456)),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "user_1".to_string(),
+                amount: Uint128::new(3456)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    let ubr = state
+        .unbond_requests
+        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
+        .unwrap();
+    assert_eq!(ubr.shares, Uint128::new(20000));
+
+    // User 2 cancels their whole request
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_2", &[]),
+        ExecuteMsg::CancelUnbond {
+            amount: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "user_2".to_string(),
+                amount: Uint128::new(69420)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    let ubr = state
+        .unbond_requests
+        .may_load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_2")))
+        .unwrap();
+    assert_eq!(ubr, None);
+
+    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        pending_batch,
+        PendingBatch {
+            id: 1,
+            ustake_to_burn: Uint128::new(20000),
+            est_unbond_start_time: 269200
+        }
+    );
+
+    // Once the batch is submitted, the request can no longer be cancelled
+    state
+        .pending_batch
+        .save(
+            deps.as_mut().storage,
+            &PendingBatch {
+                id: 2,
+                ustake_to_burn: Uint128::zero(),
+                est_unbond_start_time: 528400,
+            },
+        )
+        .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CancelUnbond {
+            amount: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::UnbondRequestNotFound {
+            id: 2,
+            user: "user_1".to_string(),
+        }
+    );
+}
+
 #[test]
 fn submitting_batch() {
     let mut deps = setup_test();
@@ -667,7 +911,9 @@ fn submitting_batch() {
         deps.as_mut(),
         mock_env_at_timestamp(269201),
         mock_info(MOCK_CONTRACT_ADDR, &[]),
-        ExecuteMsg::SubmitBatch {},
+        ExecuteMsg::SubmitBatch {
+            force: None,
+        },
     )
     .unwrap();
 
@@ -723,6 +969,8 @@ fn submitting_batch() {
             unbonding: Uint128::from(95197u128),
             available: Uint128::zero(),
             tvl_utoken: Uint128::from(95197u128 + 1037345u128),
+            buffer_utoken: Uint128::zero(),
+            buffer_target: Uint128::zero(),
         },
     );
 }
@@ -998,7 +1246,7 @@ fn withdrawing_unbonded() {
         },
         Batch {
             id: 3,
-            reconciled: false, // finished unbonding, but not reconciled; ignored
+            reconciled: false, // finished unbonding, but not reconciled; reconciled on withdrawal
             total_shares: Uint128::new(45678),
             utoken_unclaimed: Uint128::new(47276), // 1.035 Token per Stake
             est_unbond_end_time: 20000,
@@ -1038,29 +1286,54 @@ fn withdrawing_unbonded() {
         mock_info("user_1", &[]),
         ExecuteMsg::WithdrawUnbonded {
             receiver: None,
+            batch_ids: None,
+            limit: None,
         },
     )
     .unwrap_err();
 
-    assert_eq!(err, StdError::generic_err("withdrawable amount is zero"));
+    assert_eq!(err, ContractError::NothingToWithdraw);
 
-    // Attempt to withdraw once batches 1 and 2 have finished unbonding, but 3 has not yet
+    // Attempt to withdraw once batches 1, 2 and 3 have finished unbonding
+    //
+    // Batch 3 is reconciled first. Only 45,000 utoken were received instead of 47,276, so 2,276
+    // are deducted from it
     //
     // Withdrawable from batch 1: 95,197 * 23,456 / 92,876 = 24,042
     // Withdrawable from batch 2: 35,604
-    // Total withdrawable: 24,042 + 35,604 = 59,646
+    // Withdrawable from batch 3: 45,000
+    // Total withdrawable: 24,042 + 35,604 + 45,000 = 104,646
     //
     // Batch 1 should be updated:
     // Total shares: 92,876 - 23,456 = 69,420
     // Unclaimed utoken: 95,197 - 24,042 = 71,155
     //
-    // Batch 2 is completely withdrawn, should be purged from storage
+    // Batches 2 and 3 are completely withdrawn, should be purged from storage
+    deps.querier.set_bank_balances(&[Coin::new(45000, CONTRACT_DENOM)]);
+
+    let res: WithdrawableResponse = query_helper_env(
+        deps.as_ref(),
+        QueryMsg::Withdrawable {
+            user: "user_1".to_string(),
+        },
+        25000,
+    );
+    assert_eq!(
+        res,
+        WithdrawableResponse {
+            batch_ids: vec![1, 2, 3],
+            utoken: Uint128::new(104646),
+        }
+    );
+
     let res = execute(
         deps.as_mut(),
         mock_env_at_timestamp(25000),
         mock_info("user_1", &[]),
         ExecuteMsg::WithdrawUnbonded {
             receiver: None,
+            batch_ids: None,
+            limit: None,
         },
     )
     .unwrap();
@@ -1070,9 +1343,15 @@ fn withdrawing_unbonded() {
         res.messages[0],
         SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
             to_address: "user_1".to_string(),
-            amount: vec![Coin::new(59646, CONTRACT_DENOM)]
+            amount: vec![Coin::new(104646, CONTRACT_DENOM)]
         }))
     );
+    assert_eq!(
+        res.events[0],
+        Event::new("erishub/reconciled")
+            .add_attribute("ids", "3")
+            .add_attribute("utoken_deducted", "2276")
+    );
 
     // Previous batches should have been updated
     let batch = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap();
@@ -1095,6 +1374,14 @@ fn withdrawing_unbonded() {
         }
     );
 
+    let err = state.previous_batches.load(deps.as_ref().storage, 3u64).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::NotFound {
+            kind: "eris_staking::hub::Batch".to_string()
+        }
+    );
+
     // User 1's unbond requests in batches 1 and 2 should have been deleted
     let err1 = state
         .unbond_requests
@@ -1125,6 +1412,8 @@ fn withdrawing_unbonded() {
         mock_info("user_3", &[]),
         ExecuteMsg::WithdrawUnbonded {
             receiver: Some("user_2".to_string()),
+            batch_ids: None,
+            limit: None,
         },
     )
     .unwrap();
@@ -1161,56 +1450,703 @@ fn withdrawing_unbonded() {
 }
 
 #[test]
-fn adding_validator() {
+fn withdrawing_unbonded_selectively() {
     let mut deps = setup_test();
     let state = State::default();
 
-    let err = execute(
+    // Batches 1, 2 and 3 have finished unbonding; batch 4 has not
+    let previous_batches = vec![
+        (1, 20000, 20400, 10000),
+        (2, 10000, 10300, 20000),
+        (3, 5000, 5250, 20000),
+        (4, 3000, 3000, 30000),
+    ];
+
+    for (id, total_shares, utoken_unclaimed, est_unbond_end_time) in previous_batches {
+        state
+            .previous_batches
+            .save(
+                deps.as_mut().storage,
+                id,
+                &Batch {
+                    id,
+                    reconciled: true,
+                    total_shares: Uint128::new(total_shares),
+                    utoken_unclaimed: Uint128::new(utoken_unclaimed),
+                    est_unbond_end_time,
+                },
+            )
+            .unwrap();
+    }
+
+    let unbond_requests = vec![
+        (1, "user_1", 10000),
+        (1, "user_2", 10000),
+        (2, "user_1", 10000),
+        (3, "user_1", 5000),
+        (4, "user_1", 3000),
+    ];
+
+    for (id, user, shares) in unbond_requests {
+        state
+            .unbond_requests
+            .save(
+                deps.as_mut().storage,
+                (id, &Addr::unchecked(user)),
+                &UnbondRequest {
+                    id,
+                    user: Addr::unchecked(user),
+                    shares: Uint128::new(shares),
+                },
+            )
+            .unwrap();
+    }
+
+    // Withdrawable: 20400 * 10000 / 20000 + 10300 + 5250 = 25750
+    let res: WithdrawableResponse = query_helper_env(
+        deps.as_ref(),
+        QueryMsg::Withdrawable {
+            user: "user_1".to_string(),
+        },
+        25000,
+    );
+    assert_eq!(
+        res,
+        WithdrawableResponse {
+            batch_ids: vec![1, 2, 3],
+            utoken: Uint128::new(25750),
+        }
+    );
+
+    // Only batch 2 is withdrawn; batch 4 has not finished unbonding
+    let res = execute(
         deps.as_mut(),
-        mock_env(),
-        mock_info("jake", &[]),
-        ExecuteMsg::AddValidator {
-            validator: "dave".to_string(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            batch_ids: Some(vec![4, 2]),
+            limit: None,
         },
     )
-    .unwrap_err();
+    .unwrap();
 
-    assert_eq!(err, StdError::generic_err("unauthorized: sender is not owner"));
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(10300, CONTRACT_DENOM)]
+        }))
+    );
 
     let err = execute(
         deps.as_mut(),
-        mock_env(),
-        mock_info("owner", &[]),
-        ExecuteMsg::AddValidator {
-            validator: "alice".to_string(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            batch_ids: Some(vec![2, 4]),
+            limit: None,
         },
     )
     .unwrap_err();
 
-    assert_eq!(err, StdError::generic_err("validator is already whitelisted"));
+    assert_eq!(err, ContractError::NothingToWithdraw);
 
+    // Only the oldest of the remaining batches is withdrawn
     let res = execute(
         deps.as_mut(),
-        mock_env(),
-        mock_info("owner", &[]),
-        ExecuteMsg::AddValidator {
-            validator: "dave".to_string(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            batch_ids: None,
+            limit: Some(1),
         },
     )
     .unwrap();
 
-    assert_eq!(res.messages.len(), 0);
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(10200, CONTRACT_DENOM)]
+        }))
+    );
 
-    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    let res: WithdrawableResponse = query_helper_env(
+        deps.as_ref(),
+        QueryMsg::Withdrawable {
+            user: "user_1".to_string(),
+        },
+        25000,
+    );
     assert_eq!(
-        validators,
-        vec![
+        res,
+        WithdrawableResponse {
+            batch_ids: vec![3],
+            utoken: Uint128::new(5250),
+        }
+    );
+}
+
+#[test]
+fn distributing_unbonded() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // User 3 opts in, then opts out when queuing more
+    let queued = vec![
+        ("user_1", 10000, Some(true)),
+        ("user_2", 20000, None),
+        ("user_3", 5000, Some(true)),
+        ("user_3", 5000, Some(false)),
+    ];
+
+    for (user, amount, auto_claim) in queued {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(12345),
+            mock_info(STAKE_DENOM, &[]),
+            ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: user.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_binary(&ReceiveMsg::QueueUnbond {
+                    receiver: None,
+                    auto_claim,
+                })
+                .unwrap(),
+            }),
+        )
+        .unwrap();
+    }
+
+    let auto_claims = state
+        .auto_claims
+        .keys(deps.as_ref().storage, None, None, Order::Ascending)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(auto_claims, vec![(1, Addr::unchecked("user_1"))]);
+
+    // Batch 1 is submitted, and user 4 opts in in batch 2
+    state
+        .previous_batches
+        .save(
+            deps.as_mut().storage,
+            1,
+            &Batch {
+                id: 1,
+                reconciled: true,
+                total_shares: Uint128::new(40000),
+                utoken_unclaimed: Uint128::new(41000),
+                est_unbond_end_time: 20000,
+            },
+        )
+        .unwrap();
+    state
+        .pending_batch
+        .save(
+            deps.as_mut().storage,
+            &PendingBatch {
+                id: 2,
+                ustake_to_burn: Uint128::zero(),
+                est_unbond_start_time: 528400,
+            },
+        )
+        .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_4".to_string(),
+            amount: Uint128::new(7000),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                auto_claim: Some(true),
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    // Nothing can be distributed before batch 1 has finished unbonding
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(15000),
+        mock_info("keeper", &[]),
+        ExecuteMsg::DistributeUnbonded {
+            limit: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::NothingToDistribute);
+
+    // User 1's request was sold in the meantime; it is paid to its holder:
+    // 41000 * 10000 / 40000 = 10250
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(15000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::TransferNft {
+            recipient: "user_5".to_string(),
+            token_id: "1/user_1".to_string(),
+        },
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("keeper", &[]),
+        ExecuteMsg::DistributeUnbonded {
+            limit: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_5".to_string(),
+            amount: vec![Coin::new(10250, CONTRACT_DENOM)]
+        }))
+    );
+
+    let batch = state.previous_batches.load(deps.as_ref().storage, 1).unwrap();
+    assert_eq!(batch.total_shares, Uint128::new(30000));
+    assert_eq!(batch.utoken_unclaimed, Uint128::new(30750));
+
+    let ubr = state
+        .unbond_requests
+        .may_load(deps.as_ref().storage, (1, &Addr::unchecked("user_1")))
+        .unwrap();
+    assert_eq!(ubr, None);
+
+    // Users 2 and 3 did not opt in, and batch 2 has not finished unbonding
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("keeper", &[]),
+        ExecuteMsg::DistributeUnbonded {
+            limit: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::NothingToDistribute);
+}
+
+#[test]
+fn queuing_unbond_for_another_user() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let queue_unbond = |deps: DepsMut, sender: &str, receiver: Option<&str>, auto_claim| {
+        execute(
+            deps,
+            mock_env_at_timestamp(12345),
+            mock_info(STAKE_DENOM, &[]),
+            ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: sender.to_string(),
+                amount: Uint128::new(1),
+                msg: to_binary(&ReceiveMsg::QueueUnbond {
+                    receiver: receiver.map(String::from),
+                    auto_claim,
+                })
+                .unwrap(),
+            }),
+        )
+        .unwrap();
+    };
+
+    let auto_claims = |deps: Deps| {
+        state
+            .auto_claims
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    };
+
+    queue_unbond(deps.as_mut(), "user_1", None, Some(true));
+
+    // Queuing a dust amount on behalf of user 1 can not opt them out
+    queue_unbond(deps.as_mut(), "user_2", Some("user_1"), Some(false));
+    assert_eq!(auto_claims(deps.as_ref()), vec![(1, Addr::unchecked("user_1"))]);
+
+    // but can opt another user in
+    queue_unbond(deps.as_mut(), "user_1", Some("user_2"), Some(true));
+    assert_eq!(
+        auto_claims(deps.as_ref()),
+        vec![(1, Addr::unchecked("user_1")), (1, Addr::unchecked("user_2"))]
+    );
+
+    queue_unbond(deps.as_mut(), "user_1", None, Some(false));
+    assert_eq!(auto_claims(deps.as_ref()), vec![(1, Addr::unchecked("user_2"))]);
+}
+#[test]
+fn rebalancing_with_redelegation_cooldowns() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 300000),
+        Delegation::new("bob", 100000),
+        Delegation::new("charlie", 200000),
+    ]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Rebalance {
+            min_deviation: None,
+            max_redelegations: None,
+            force: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Redelegation::new("alice", "bob", 100000).to_cosmos_msg()),
+    );
+
+    // The redelegation to bob completes after the unbonding period: 10000 + 1814400 = 1824400
+    let completion_time =
+        state.redelegation_completion_times.load(deps.as_ref().storage, "bob").unwrap();
+    assert_eq!(completion_time, 1824400);
+
+    // Until then, bob can not be redelegated from, even if he holds more than his target
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 200000),
+        Delegation::new("bob", 300000),
+        Delegation::new("charlie", 100000),
+    ]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Rebalance {
+            min_deviation: None,
+            max_redelegations: None,
+            force: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0], check_received_coin(0));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("owner", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "bob".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::RedelegationBlocked {
+            validator: "bob".to_string(),
+            completion_time: 1824400,
+        }
+    );
+
+    // Once the redelegation has completed, bob's excess can be moved
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(1824400),
+        mock_info("worker", &[]),
+        ExecuteMsg::Rebalance {
+            min_deviation: None,
+            max_redelegations: None,
+            force: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Redelegation::new("bob", "charlie", 100000).to_cosmos_msg()),
+    );
+}
+
+#[test]
+fn rebalancing_with_threshold_and_limit() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // Target: (1300 + 900 + 800) / 3 = 1000
+    // Deviations: alice +300, bob -100, charlie -200
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 1300),
+        Delegation::new("bob", 900),
+        Delegation::new("charlie", 800),
+    ]);
+
+    let simulate = |deps: &OwnedDeps<MockStorage, MockApi, CustomQuerier>,
+                    min_deviation: Option<u128>,
+                    max_redelegations: Option<u32>| {
+        query_helper::<Vec<RedelegationResponseItem>>(
+            deps.as_ref(),
+            QueryMsg::SimulateRebalance {
+                min_deviation: min_deviation.map(Uint128::new),
+                max_redelegations,
+                force: None,
+            },
+        )
+        .into_iter()
+        .map(|rd| Redelegation::new(&rd.src, &rd.dst, rd.amount.u128()))
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        simulate(&deps, None, None),
+        vec![Redelegation::new("alice", "bob", 100), Redelegation::new("alice", "charlie", 200)],
+    );
+
+    // Bob deviates by less than 150, so he is left as he is
+    assert_eq!(simulate(&deps, Some(150), None), vec![Redelegation::new("alice", "charlie", 200)]);
+
+    // Nobody deviates by 400 or more
+    assert_eq!(simulate(&deps, Some(400), None), vec![]);
+
+    // Only the largest move is made
+    assert_eq!(simulate(&deps, None, Some(1)), vec![Redelegation::new("alice", "charlie", 200)]);
+
+    // The minimum redelegation applies to each move, independently of the deviation threshold: bob
+    // deviates by more than 50, but the move topping him up is too small to be made
+    state.min_redelegation.save(deps.as_mut().storage, &Uint128::new(150)).unwrap();
+    assert_eq!(simulate(&deps, Some(50), None), vec![Redelegation::new("alice", "charlie", 200)]);
+    state.min_redelegation.save(deps.as_mut().storage, &Uint128::zero()).unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("worker", &[]),
+        ExecuteMsg::Rebalance {
+            min_deviation: None,
+            max_redelegations: Some(1),
+            force: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Redelegation::new("alice", "charlie", 200).to_cosmos_msg()),
+    );
+    assert_eq!(res.messages[1], check_received_coin(0));
+
+    // Only the redelegation that was made is recorded
+    let completion_times = state
+        .redelegation_completion_times
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .map(|item| item.unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(completion_times, vec![String::from("charlie")]);
+}
+
+#[test]
+fn adding_validator() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+            weight: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "alice".to_string(),
+            weight: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ValidatorAlreadyWhitelisted("alice".to_string()));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+            weight: Some(200),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ValidatorNotActive("dave".to_string()));
+
+    // A jailed validator exists, but is not in the active set either
+    deps.querier.set_jailed_validators(&["dave"]);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+            weight: Some(200),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ValidatorNotActive("dave".to_string()));
+    deps.querier.set_jailed_validators(&[]);
+
+    deps.querier.set_staking_validators(&[("dave", Decimal::percent(20))]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            max_commission: Some(Decimal::percent(10)),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+            weight: Some(200),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::CommissionTooHigh {
+            commission: Decimal::percent(20),
+            max_commission: Decimal::percent(10),
+        }
+    );
+
+    deps.querier.set_staking_validators(&[("dave", Decimal::percent(10))]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+            weight: Some(200),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 0);
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        validators,
+        vec![
             String::from("alice"),
             String::from("bob"),
             String::from("charlie"),
             String::from("dave")
         ],
     );
+
+    let weights = state.load_validator_weights(deps.as_ref().storage, &validators).unwrap();
+    assert_eq!(weights, vec![100, 100, 100, 200]);
+}
+
+#[test]
+fn updating_validator_weights() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::UpdateValidatorWeights {
+            weights: vec![("alice".to_string(), 300)],
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateValidatorWeights {
+            weights: vec![("dave".to_string(), 300)],
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ValidatorNotWhitelisted("dave".to_string()));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateValidatorWeights {
+            weights: vec![("alice".to_string(), 300), ("charlie".to_string(), 50)],
+        },
+    )
+    .unwrap();
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    let weights = state.load_validator_weights(deps.as_ref().storage, &validators).unwrap();
+    assert_eq!(weights, vec![300, 100, 50]);
+
+    // Alice holds twice as much as Bob, but her weight is three times his, so she receives the
+    // deposit even though she has the largest delegation
+    deps.querier.set_bank_balances(&[coin(12345, CONTRACT_DENOM)]);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 200000),
+        Delegation::new("bob", 100000),
+        Delegation::new("charlie", 60000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 360000);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("alice", 12345).to_cosmos_msg()));
 }
 
 #[test]
@@ -1234,7 +2170,7 @@ fn removing_validator() {
     )
     .unwrap_err();
 
-    assert_eq!(err, StdError::generic_err("unauthorized: sender is not owner"));
+    assert_eq!(err, ContractError::Unauthorized);
 
     let err = execute(
         deps.as_mut(),
@@ -1246,7 +2182,7 @@ fn removing_validator() {
     )
     .unwrap_err();
 
-    assert_eq!(err, StdError::generic_err("validator is not already whitelisted"));
+    assert_eq!(err, ContractError::ValidatorNotWhitelisted("dave".to_string()));
 
     // Target: (341667 + 341667 + 341666) / 2 = 512500
     // Remainder: 0
@@ -1277,6 +2213,157 @@ fn removing_validator() {
     assert_eq!(validators, vec![String::from("alice"), String::from("bob")],);
 }
 
+#[test]
+fn removing_inactive_validators() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_validators(&[
+        ("alice", Decimal::percent(5)),
+        ("bob", Decimal::percent(5)),
+        ("charlie", Decimal::percent(5)),
+        ("dave", Decimal::percent(5)),
+    ]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+            weight: None,
+        },
+    )
+    .unwrap();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 100000),
+        Delegation::new("bob", 300000),
+        Delegation::new("charlie", 200000),
+    ]);
+
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("jake", &[]), ExecuteMsg::CheckValidators {})
+            .unwrap_err();
+
+    assert_eq!(err, ContractError::NoValidatorToRemove);
+
+    // The whitelist can not be emptied
+    deps.querier.set_staking_validators(&[]);
+
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("jake", &[]), ExecuteMsg::CheckValidators {})
+            .unwrap_err();
+
+    assert_eq!(err, ContractError::CannotRemoveAllValidators);
+
+    // Bob and Charlie have been jailed. They still exist, but are no longer in the active set
+    deps.querier
+        .set_staking_validators(&[("alice", Decimal::percent(5)), ("dave", Decimal::percent(5))]);
+    deps.querier.set_jailed_validators(&["bob", "charlie"]);
+
+    // Removing Bob: (100000 + 0 + 300000) / 2 = 200000
+    // Alice:   200000 - 100000 = 100000
+    // Dave:    200000 - 0      = 200000
+    //
+    // Removing Charlie: (200000 + 200000 + 200000) / 2 = 300000
+    // Alice:   300000 - 200000 = 100000
+    // Dave:    300000 - 200000 = 100000
+    let res =
+        execute(deps.as_mut(), mock_env(), mock_info("jake", &[]), ExecuteMsg::CheckValidators {})
+            .unwrap();
+
+    assert_eq!(res.messages.len(), 5);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Redelegation::new("bob", "alice", 100000).to_cosmos_msg()),
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(Redelegation::new("bob", "dave", 200000).to_cosmos_msg()),
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(Redelegation::new("charlie", "alice", 100000).to_cosmos_msg()),
+    );
+    assert_eq!(
+        res.messages[3],
+        SubMsg::new(Redelegation::new("charlie", "dave", 100000).to_cosmos_msg()),
+    );
+    assert_eq!(res.messages[4], check_received_coin(0));
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(validators, vec![String::from("alice"), String::from("dave")]);
+}
+
+#[test]
+fn removing_overcharging_validators() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 300000),
+        Delegation::new("bob", 300000),
+        Delegation::new("charlie", 300000),
+    ]);
+    deps.querier.set_staking_validators(&[
+        ("alice", Decimal::percent(5)),
+        ("bob", Decimal::percent(20)),
+        ("charlie", Decimal::percent(10)),
+    ]);
+
+    // No cap is configured by default
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("jake", &[]), ExecuteMsg::CheckValidators {})
+            .unwrap_err();
+
+    assert_eq!(err, ContractError::NoValidatorToRemove);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            max_commission: Some(Decimal::percent(101)),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::GreaterThanOne("max_commission".to_string()));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            max_commission: Some(Decimal::percent(10)),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap();
+
+    // Bob raised his commission above the cap; Charlie is exactly at the cap and stays
+    // Target: (300000 + 300000 + 300000) / 2 = 450000
+    let res =
+        execute(deps.as_mut(), mock_env(), mock_info("jake", &[]), ExecuteMsg::CheckValidators {})
+            .unwrap();
+
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Redelegation::new("bob", "alice", 150000).to_cosmos_msg()),
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(Redelegation::new("bob", "charlie", 150000).to_cosmos_msg()),
+    );
+    assert_eq!(res.messages[2], check_received_coin(0));
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(validators, vec![String::from("alice"), String::from("charlie")]);
+}
+
 #[test]
 fn transferring_ownership() {
     let mut deps = setup_test();
@@ -1292,7 +2379,7 @@ fn transferring_ownership() {
     )
     .unwrap_err();
 
-    assert_eq!(err, StdError::generic_err("unauthorized: sender is not owner"));
+    assert_eq!(err, ContractError::Unauthorized);
 
     let res = execute(
         deps.as_mut(),
@@ -1317,7 +2404,7 @@ fn transferring_ownership() {
     )
     .unwrap_err();
 
-    assert_eq!(err, StdError::generic_err("unauthorized: sender is not new owner"));
+    assert_eq!(err, ContractError::UnauthorizedNewOwner);
 
     let res =
         execute(deps.as_mut(), mock_env(), mock_info("jake", &[]), ExecuteMsg::AcceptOwnership {})
@@ -1342,8 +2429,11 @@ fn update_fee() {
     assert_eq!(
         config,
         FeeConfig {
-            protocol_fee_contract: Addr::unchecked("fee"),
-            protocol_reward_fee: Decimal::from_ratio(1u128, 100u128)
+            protocol_fee_recipients: vec![(Addr::unchecked("fee"), Decimal::one())],
+            protocol_reward_fee: Decimal::from_ratio(1u128, 100u128),
+            deposit_fee: Decimal::zero(),
+            deposit_fee_source: DepositFeeSource::Deposit,
+            withdrawal_fee: Decimal::zero(),
         }
     );
 
@@ -1351,34 +2441,80 @@ fn update_fee() {
         deps.as_mut(),
         mock_env(),
         mock_info("jake", &[]),
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract: None,
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
             protocol_reward_fee: Some(Decimal::from_ratio(11u128, 100u128)),
-        },
+            ..UpdateConfigMsg::default()
+        }),
     )
     .unwrap_err();
-    assert_eq!(err, StdError::generic_err("unauthorized: sender is not owner"));
+    assert_eq!(err, ContractError::Unauthorized);
 
     let err = execute(
         deps.as_mut(),
         mock_env(),
         mock_info("owner", &[]),
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract: None,
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
             protocol_reward_fee: Some(Decimal::from_ratio(11u128, 100u128)),
-        },
+            ..UpdateConfigMsg::default()
+        }),
     )
     .unwrap_err();
-    assert_eq!(err, StdError::generic_err("'protocol_reward_fee' greater than max"));
+    assert_eq!(err, ContractError::GreaterThanMax("protocol_reward_fee".to_string()));
+
+    // The single recipient field it replaced is rejected rather than ignored
+    let res =
+        from_slice::<ExecuteMsg>(br#"{"update_config":{"protocol_fee_contract":"treasury"}}"#);
+    assert!(res.unwrap_err().to_string().contains("unknown field `protocol_fee_contract`"));
+
+    let invalid_recipients = vec![
+        (vec![], ContractError::InvalidProtocolFeeShares),
+        (
+            vec![("treasury", Decimal::percent(60)), ("insurance", Decimal::percent(30))],
+            ContractError::InvalidProtocolFeeShares,
+        ),
+        (
+            vec![("treasury", Decimal::percent(60)), ("insurance", Decimal::percent(50))],
+            ContractError::InvalidProtocolFeeShares,
+        ),
+        (
+            vec![("treasury", Decimal::one()), ("insurance", Decimal::zero())],
+            ContractError::ZeroProtocolFeeShare("insurance".to_string()),
+        ),
+        (
+            vec![("treasury", Decimal::percent(50)), ("treasury", Decimal::percent(50))],
+            ContractError::DuplicateProtocolFeeRecipient("treasury".to_string()),
+        ),
+    ];
+
+    for (recipients, expected) in invalid_recipients {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+                protocol_fee_recipients: Some(
+                    recipients.iter().map(|(a, share)| (a.to_string(), *share)).collect(),
+                ),
+                ..UpdateConfigMsg::default()
+            }),
+        )
+        .unwrap_err();
+        assert_eq!(err, expected);
+    }
 
     let res = execute(
         deps.as_mut(),
         mock_env(),
         mock_info("owner", &[]),
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract: Some("fee-new".to_string()),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            protocol_fee_recipients: Some(vec![
+                ("treasury".to_string(), Decimal::percent(50)),
+                ("insurance".to_string(), Decimal::percent(30)),
+                ("incentives".to_string(), Decimal::percent(20)),
+            ]),
             protocol_reward_fee: Some(Decimal::from_ratio(10u128, 100u128)),
-        },
+            ..UpdateConfigMsg::default()
+        }),
     )
     .unwrap();
 
@@ -1388,8 +2524,112 @@ fn update_fee() {
     assert_eq!(
         config,
         FeeConfig {
-            protocol_fee_contract: Addr::unchecked("fee-new"),
-            protocol_reward_fee: Decimal::from_ratio(10u128, 100u128)
+            protocol_fee_recipients: vec![
+                (Addr::unchecked("treasury"), Decimal::percent(50)),
+                (Addr::unchecked("insurance"), Decimal::percent(30)),
+                (Addr::unchecked("incentives"), Decimal::percent(20)),
+            ],
+            protocol_reward_fee: Decimal::from_ratio(10u128, 100u128),
+            deposit_fee: Decimal::zero(),
+            deposit_fee_source: DepositFeeSource::Deposit,
+            withdrawal_fee: Decimal::zero(),
+        }
+    );
+}
+
+#[test]
+fn updating_delegation_strategy() {
+    let mut deps = setup_test();
+
+    deps.querier.set_bank_balances(&[coin(12345, CONTRACT_DENOM)]);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 200000),
+        Delegation::new("bob", 100000),
+        Delegation::new("charlie", 150000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 450000);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateValidatorWeights {
+            weights: vec![("alice".to_string(), 300)],
+        },
+    )
+    .unwrap();
+
+    let bond = |deps: &mut OwnedDeps<MockStorage, MockApi, CustomQuerier>| {
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("user_1", &[Coin::new(12345, CONTRACT_DENOM)]),
+            ExecuteMsg::Bond {
+                receiver: None,
+            },
+        )
+        .unwrap();
+        res.messages[0].clone()
+    };
+
+    // Weighted: alice has the smallest delegation relative to her weight
+    assert_eq!(bond(&mut deps), SubMsg::new(Delegation::new("alice", 12345).to_cosmos_msg()));
+
+    // Uniform: weights are ignored, bob has the smallest delegation
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            delegation_strategy: Some(DelegationStrategyConfig::Uniform),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap();
+    assert_eq!(bond(&mut deps), SubMsg::new(Delegation::new("bob", 12345).to_cosmos_msg()));
+
+    // External: weights are provided by another contract, validators it omits get a zero weight
+    deps.querier.validator_weights = vec![("charlie".to_string(), 1), ("alice".to_string(), 1)];
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            delegation_strategy: Some(DelegationStrategyConfig::External {
+                contract: "weights_provider".to_string(),
+            }),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap();
+    assert_eq!(bond(&mut deps), SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg()));
+
+    // Rebalancing moves everything out of bob; alice and charlie each target 450000 / 2 = 225000
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("worker", &[]),
+        ExecuteMsg::Rebalance {
+            min_deviation: None,
+            max_redelegations: None,
+            force: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Redelegation::new("bob", "alice", 25000).to_cosmos_msg())
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(Redelegation::new("bob", "charlie", 75000).to_cosmos_msg())
+    );
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(
+        res.delegation_strategy,
+        DelegationStrategyConfig::External {
+            contract: "weights_provider".to_string()
         }
     );
 }
@@ -1717,7 +2957,8 @@ fn computing_undelegations() {
     // Alice:   400 - (149 + 1) = 250
     // Bob:     300 - (149 + 1) = 150
     // Charlie: 200 - (149 + 0) = 51
-    let new_undelegations = compute_undelegations(Uint128::new(451), &current_delegations);
+    let new_undelegations =
+        compute_undelegations(Uint128::new(451), &current_delegations, &[100, 100, 100]);
     let expected = vec![
         Undelegation::new("alice", 250),
         Undelegation::new("bob", 150),
@@ -1728,7 +2969,7 @@ fn computing_undelegations() {
 
 #[test]
 fn computing_redelegations_for_removal() {
-    let current_delegations = vec![
+    let current_delegations = [
         Delegation::new("alice", 13000),
         Delegation::new("bob", 12000),
         Delegation::new("charlie", 11000),
@@ -1748,7 +2989,11 @@ fn computing_redelegations_for_removal() {
     ];
 
     assert_eq!(
-        compute_redelegations_for_removal(&current_delegations[3], &current_delegations[..3]),
+        compute_redelegations_for_removal(
+            &current_delegations[3],
+            &current_delegations[..3],
+            &[100, 100, 100]
+        ),
         expected,
     );
 }
@@ -1793,7 +3038,164 @@ fn computing_redelegations_for_rebalancing() {
         Redelegation::new("charlie", "evan", 38126),
     ];
 
-    assert_eq!(compute_redelegations_for_rebalancing(&current_delegations), expected,);
+    assert_eq!(
+        compute_redelegations_for_rebalancing(&current_delegations, &[1, 1, 1, 1, 1], &[], 0, 0),
+        expected,
+    );
+
+    // Charlie recently received a redelegation, so only alice's excess can be moved
+    let expected = vec![Redelegation::new("alice", "bob", 28948)];
+
+    assert_eq!(
+        compute_redelegations_for_rebalancing(
+            &current_delegations,
+            &[1, 1, 1, 1, 1],
+            &["charlie".to_string()],
+            0,
+            0
+        ),
+        expected,
+    );
+}
+
+#[test]
+fn computing_minimal_redelegations_for_rebalancing() {
+    // Target: 400 / 4 = 100
+    // Deviations: alice +5, bob +3, charlie -3, dave -5
+    //
+    // Pairing the validators in order would take 3 moves:
+    // alice --(3)--> charlie, alice --(2)--> dave, bob --(3)--> dave
+    // Settling {alice, dave} and {bob, charlie} separately only takes 2
+    let current_delegations = vec![
+        Delegation::new("alice", 105),
+        Delegation::new("bob", 103),
+        Delegation::new("charlie", 97),
+        Delegation::new("dave", 95),
+    ];
+
+    let expected =
+        vec![Redelegation::new("alice", "dave", 5), Redelegation::new("bob", "charlie", 3)];
+
+    assert_eq!(
+        compute_redelegations_for_rebalancing(&current_delegations, &[1, 1, 1, 1], &[], 0, 0),
+        expected,
+    );
+
+    // Target: 400 / 4 = 100
+    // Deviations: alice +10, charlie -7, dave -3
+    //
+    // With a minimum deviation of 5, dave is left as he is, and what alice has left after topping
+    // up charlie stays with her
+    let current_delegations = vec![
+        Delegation::new("alice", 110),
+        Delegation::new("bob", 100),
+        Delegation::new("charlie", 93),
+        Delegation::new("dave", 97),
+    ];
+
+    let expected = vec![Redelegation::new("alice", "charlie", 7)];
+
+    assert_eq!(
+        compute_redelegations_for_rebalancing(&current_delegations, &[1, 1, 1, 1], &[], 5, 0),
+        expected,
+    );
+
+    // A minimum deviation of 8 leaves out charlie as well, so nothing is moved
+    assert_eq!(
+        compute_redelegations_for_rebalancing(&current_delegations, &[1, 1, 1, 1], &[], 8, 0),
+        vec![],
+    );
+
+    // With a minimum redelegation of 5 instead, dave is still topped up, but by a move too small
+    // to be made
+    assert_eq!(
+        compute_redelegations_for_rebalancing(&current_delegations, &[1, 1, 1, 1], &[], 0, 5),
+        expected,
+    );
+
+    // With too many validators to go through all subsets, deviations that cancel each other out
+    // are still paired
+    // Target: 1400 / 14 = 100
+    // Deviations: val_0 +10, val_1 -10, val_2 +20, val_3 -20, ...
+    let current_delegations = (0..14)
+        .map(|i| {
+            let deviation = (i / 2 + 1) * 10;
+            let amount = if i % 2 == 0 {
+                100 + deviation
+            } else {
+                100 - deviation
+            };
+            Delegation::new(&format!("val_{}", i), amount)
+        })
+        .collect::<Vec<_>>();
+
+    let expected = (0..7)
+        .map(|i| {
+            Redelegation::new(
+                &format!("val_{}", 2 * i),
+                &format!("val_{}", 2 * i + 1),
+                (i + 1) * 10,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        compute_redelegations_for_rebalancing(&current_delegations, &[1; 14], &[], 0, 0),
+        expected,
+    );
+}
+
+#[test]
+fn computing_target_delegations() {
+    // Equal weights behave like an even split, the remainder going to the first validators
+    assert_eq!(compute_target_delegations(1000, &[100, 100, 100]), vec![334, 333, 333]);
+
+    // 1000 * 3 / 6 = 500, 1000 * 2 / 6 = 333, 1000 * 1 / 6 = 166; remainder 1
+    assert_eq!(compute_target_delegations(1000, &[3, 2, 1]), vec![501, 333, 166]);
+
+    // Validators with a zero weight never receive any of the remainder
+    assert_eq!(compute_target_delegations(1001, &[0, 1, 1]), vec![0, 501, 500]);
+
+    // If all weights are zero, fall back to an even split
+    assert_eq!(compute_target_delegations(10, &[0, 0, 0]), vec![4, 3, 3]);
+}
+
+#[test]
+fn computing_weighted_redelegations() {
+    let current_delegations = vec![
+        Delegation::new("alice", 10000),
+        Delegation::new("bob", 10000),
+        Delegation::new("charlie", 10000),
+    ];
+
+    // Targets: alice 15000, bob 10000, charlie 5000
+    assert_eq!(
+        compute_redelegations_for_rebalancing(&current_delegations, &[3, 2, 1], &[], 0, 0),
+        vec![Redelegation::new("charlie", "alice", 5000)],
+    );
+
+    // Unbonding 6000 leaves 24000 to distribute; targets: alice 12000, bob 8000, charlie 4000
+    assert_eq!(
+        compute_undelegations(Uint128::new(6000), &current_delegations, &[3, 2, 1]),
+        vec![Undelegation::new("bob", 2000), Undelegation::new("charlie", 4000)],
+    );
+
+    // Removing charlie; targets of the remaining: alice 18000, bob 12000
+    assert_eq!(
+        compute_redelegations_for_removal(
+            &current_delegations[2],
+            &current_delegations[..2],
+            &[3, 2]
+        ),
+        vec![
+            Redelegation::new("charlie", "alice", 8000),
+            Redelegation::new("charlie", "bob", 2000)
+        ],
+    );
+
+    // The validator with the smallest delegation per weight receives new delegations
+    assert_eq!(find_validator_to_delegate(&current_delegations, &[3, 2, 1]).validator, "alice");
+    assert_eq!(find_validator_to_delegate(&current_delegations, &[0, 2, 1]).validator, "bob");
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -1814,10 +3216,15 @@ fn parsing_coin() {
     );
 
     let err = parse_coin("69420").unwrap_err();
-    assert_eq!(err, StdError::generic_err("failed to parse coin: 69420"));
+    assert_eq!(err, ContractError::InvalidCoin("69420".to_string()));
 
     let err = parse_coin("ngmi").unwrap_err();
-    assert_eq!(err, StdError::generic_err("Parsing u128: cannot parse integer from empty string"));
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "Parsing u128: cannot parse integer from empty string"
+        ))
+    );
 }
 
 #[test]
@@ -1855,23 +3262,26 @@ fn adding_coins() {
 #[test]
 fn receiving_funds() {
     let err = parse_received_fund(&[], CONTRACT_DENOM).unwrap_err();
-    assert_eq!(err, StdError::generic_err("must deposit exactly one coin; received 0"));
+    assert_eq!(err, ContractError::InvalidFundsCount(0));
 
     let err = parse_received_fund(
         &[Coin::new(12345, "uatom"), Coin::new(23456, CONTRACT_DENOM)],
         CONTRACT_DENOM,
     )
     .unwrap_err();
-    assert_eq!(err, StdError::generic_err("must deposit exactly one coin; received 2"));
+    assert_eq!(err, ContractError::InvalidFundsCount(2));
 
     let err = parse_received_fund(&[Coin::new(12345, "uatom")], CONTRACT_DENOM).unwrap_err();
     assert_eq!(
         err,
-        StdError::generic_err(format!("expected {} deposit, received uatom", CONTRACT_DENOM))
+        ContractError::InvalidDenom {
+            expected: CONTRACT_DENOM.to_string(),
+            received: "uatom".to_string(),
+        }
     );
 
     let err = parse_received_fund(&[Coin::new(0, CONTRACT_DENOM)], CONTRACT_DENOM).unwrap_err();
-    assert_eq!(err, StdError::generic_err("deposit amount must be non-zero"));
+    assert_eq!(err, ContractError::ZeroDeposit);
 
     let amount = parse_received_fund(&[Coin::new(69420, CONTRACT_DENOM)], CONTRACT_DENOM).unwrap();
     assert_eq!(amount, Uint128::new(69420));
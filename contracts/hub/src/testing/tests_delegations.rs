@@ -0,0 +1,95 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{coin, Decimal, Uint128};
+
+use eris_staking::hub::{DelegationResponseItem, ExecuteMsg, QueryMsg};
+
+use crate::constants::CONTRACT_DENOM;
+use crate::contract::execute;
+use crate::types::Delegation;
+
+use super::helpers::query_helper;
+use super::tests_default::setup_test;
+
+#[test]
+fn querying_delegations() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 500000),
+        Delegation::new("bob", 300000),
+        Delegation::new("charlie", 200000),
+    ]);
+    deps.querier.set_accumulated_rewards("alice", &[coin(123, CONTRACT_DENOM), coin(45, "uusd")]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateValidatorWeights {
+            weights: vec![
+                ("alice".to_string(), 2),
+                ("bob".to_string(), 1),
+                ("charlie".to_string(), 1),
+            ],
+        },
+    )
+    .unwrap();
+
+    let res: Vec<DelegationResponseItem> = query_helper(deps.as_ref(), QueryMsg::Delegations {});
+    assert_eq!(
+        res,
+        vec![
+            DelegationResponseItem {
+                validator: "alice".to_string(),
+                amount: Uint128::new(500000),
+                share: Decimal::percent(50),
+                target_share: Decimal::percent(50),
+                pending_rewards: vec![coin(123, CONTRACT_DENOM), coin(45, "uusd")],
+            },
+            DelegationResponseItem {
+                validator: "bob".to_string(),
+                amount: Uint128::new(300000),
+                share: Decimal::percent(30),
+                target_share: Decimal::percent(25),
+                pending_rewards: vec![],
+            },
+            DelegationResponseItem {
+                validator: "charlie".to_string(),
+                amount: Uint128::new(200000),
+                share: Decimal::percent(20),
+                target_share: Decimal::percent(25),
+                pending_rewards: vec![],
+            },
+        ]
+    );
+}
+
+#[test]
+fn querying_delegations_with_zero_weights() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 150000),
+        Delegation::new("bob", 100000),
+        Delegation::new("charlie", 50000),
+    ]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateValidatorWeights {
+            weights: vec![
+                ("alice".to_string(), 0),
+                ("bob".to_string(), 0),
+                ("charlie".to_string(), 0),
+            ],
+        },
+    )
+    .unwrap();
+
+    // Like rebalancing, all-zero weights target an even split
+    let res: Vec<DelegationResponseItem> = query_helper(deps.as_ref(), QueryMsg::Delegations {});
+    let target_shares = res.iter().map(|d| d.target_share).collect::<Vec<_>>();
+    assert_eq!(target_shares, vec![Decimal::from_ratio(1u128, 3u128); 3]);
+}
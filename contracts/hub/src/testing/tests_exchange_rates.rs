@@ -0,0 +1,225 @@
+use cosmwasm_std::testing::{mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{Coin, Decimal, Order, Uint128};
+
+use eris_staking::hub::{
+    AprResponse, CallbackMsg, ExchangeRateSnapshot, ExecuteMsg, PendingBatch, QueryMsg,
+};
+
+use crate::constants::{CONTRACT_DENOM, EXCHANGE_RATE_HISTORY_SIZE, SECONDS_PER_YEAR};
+use crate::contract::execute;
+use crate::state::State;
+use crate::types::Delegation;
+
+use super::helpers::{mock_env_at_timestamp, query_helper};
+use super::tests_default::{setup_test, STAKE_DENOM};
+
+fn snapshot(time: u64, total_utoken: u128, total_ustake: u128) -> ExchangeRateSnapshot {
+    ExchangeRateSnapshot {
+        time,
+        total_utoken: Uint128::new(total_utoken),
+        total_ustake: Uint128::new(total_ustake),
+        exchange_rate: Decimal::from_ratio(total_utoken, total_ustake),
+    }
+}
+
+#[test]
+fn recording_exchange_rates() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334),
+        Delegation::new("bob", 333333),
+        Delegation::new("charlie", 333333),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(1000, CONTRACT_DENOM)])
+        .unwrap();
+
+    // 1% of the rewards is taken as protocol fee, the remaining 990 utoken are reinvested
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334),
+        Delegation::new("bob", 334323),
+        Delegation::new("charlie", 333333),
+    ]);
+
+    state
+        .pending_batch
+        .save(
+            deps.as_mut().storage,
+            &PendingBatch {
+                id: 1,
+                ustake_to_burn: Uint128::new(100000),
+                est_unbond_start_time: 30000,
+            },
+        )
+        .unwrap();
+
+    // 100000 ustake are burned for 1000990 * 100000 / 1000000 = 100099 utoken
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(30000),
+        mock_info("jake", &[]),
+        ExecuteMsg::SubmitBatch {
+            force: None,
+        },
+    )
+    .unwrap();
+
+    let res: Vec<ExchangeRateSnapshot> = query_helper(
+        deps.as_ref(),
+        QueryMsg::ExchangeRates {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![snapshot(20000, 1000990, 1000000), snapshot(30000, 900891, 900000)]);
+
+    let res: Vec<ExchangeRateSnapshot> = query_helper(
+        deps.as_ref(),
+        QueryMsg::ExchangeRates {
+            start_after: Some(20000),
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![snapshot(30000, 900891, 900000)]);
+}
+
+#[test]
+fn pruning_exchange_rates() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    for i in 0..(EXCHANGE_RATE_HISTORY_SIZE + 5) {
+        state
+            .save_exchange_rate(
+                deps.as_mut().storage,
+                &snapshot(i * 100, 1000000 + i as u128, 1000000),
+            )
+            .unwrap();
+    }
+
+    // A snapshot taken at the same time as the latest one replaces it
+    let last = (EXCHANGE_RATE_HISTORY_SIZE + 4) * 100;
+    state.save_exchange_rate(deps.as_mut().storage, &snapshot(last, 2000000, 1000000)).unwrap();
+
+    let count = state.exchange_rate_count.load(deps.as_ref().storage).unwrap();
+    assert_eq!(count, EXCHANGE_RATE_HISTORY_SIZE);
+
+    let times = state
+        .exchange_rates
+        .keys(deps.as_ref().storage, None, None, Order::Ascending)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(times.len() as u64, EXCHANGE_RATE_HISTORY_SIZE);
+    assert_eq!(times[0], 500);
+
+    let res: Vec<ExchangeRateSnapshot> = query_helper(
+        deps.as_ref(),
+        QueryMsg::ExchangeRates {
+            start_after: Some(last - 200),
+            limit: Some(1),
+        },
+    );
+    assert_eq!(res, vec![snapshot(last - 100, 1000000 + 103, 1000000)]);
+
+    let res: AprResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::Apr {
+            window: Some(0),
+        },
+    );
+    assert_eq!(res.end, Some(snapshot(last, 2000000, 1000000)));
+}
+
+#[test]
+fn querying_apr() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let res: AprResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::Apr {
+            window: None,
+        },
+    );
+    assert_eq!(
+        res,
+        AprResponse {
+            apr: None,
+            start: None,
+            end: None,
+        }
+    );
+
+    let half_year = SECONDS_PER_YEAR / 2;
+    for s in [
+        snapshot(0, 1000000, 1000000),
+        snapshot(half_year, 1050000, 1000000),
+        snapshot(SECONDS_PER_YEAR, 1102500, 1000000),
+    ] {
+        state.save_exchange_rate(deps.as_mut().storage, &s).unwrap();
+    }
+
+    // Over the full history, the exchange rate grew from 1 to 1.1025 in a year
+    let res: AprResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::Apr {
+            window: None,
+        },
+    );
+    assert_eq!(
+        res,
+        AprResponse {
+            apr: Some(Decimal::from_ratio(1025u128, 10000u128)),
+            start: Some(snapshot(0, 1000000, 1000000)),
+            end: Some(snapshot(SECONDS_PER_YEAR, 1102500, 1000000)),
+        }
+    );
+
+    // Over the last half year, it grew by 5%, which is 10% annualized
+    let res: AprResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::Apr {
+            window: Some(half_year),
+        },
+    );
+    assert_eq!(res.apr, Some(Decimal::percent(10)));
+    assert_eq!(res.start, Some(snapshot(half_year, 1050000, 1000000)));
+
+    // A window holding only the latest snapshot gives no APR
+    let res: AprResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::Apr {
+            window: Some(100),
+        },
+    );
+    assert_eq!(res.apr, None);
+
+    // A decrease of the exchange rate, e.g. after slashing, counts as zero
+    state
+        .save_exchange_rate(
+            deps.as_mut().storage,
+            &snapshot(SECONDS_PER_YEAR + 100, 1000000, 1000000),
+        )
+        .unwrap();
+
+    let res: AprResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::Apr {
+            window: Some(100),
+        },
+    );
+    assert_eq!(res.apr, Some(Decimal::zero()));
+}
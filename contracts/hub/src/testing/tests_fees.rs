@@ -0,0 +1,324 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{
+    coin, to_binary, Addr, BankMsg, CosmosMsg, Decimal, DepsMut, Event, SubMsg, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+
+use eris_staking::hub::{
+    Batch, DepositFeeSource, ExecuteMsg, QueryMsg, UnbondRequest, UpdateConfigMsg,
+    WithdrawableResponse,
+};
+
+use crate::constants::CONTRACT_DENOM;
+use crate::contract::execute;
+use crate::error::ContractError;
+use crate::state::State;
+use crate::types::{Delegation, SendFee};
+
+use super::helpers::{mock_env_at_timestamp, query_helper_env, update_config};
+use super::tests_default::{check_received_coin, setup_test, STAKE_DENOM};
+
+fn split_protocol_fee(deps: DepsMut) {
+    update_config(
+        deps,
+        UpdateConfigMsg {
+            protocol_fee_recipients: Some(vec![
+                ("treasury".to_string(), Decimal::percent(60)),
+                ("insurance".to_string(), Decimal::percent(40)),
+            ]),
+            ..UpdateConfigMsg::default()
+        },
+    );
+}
+
+fn mint_msg(recipient: &str, amount: u128) -> SubMsg {
+    SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: STAKE_DENOM.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: recipient.to_string(),
+            amount: Uint128::new(amount),
+        })
+        .unwrap(),
+        funds: vec![],
+    }))
+}
+
+#[test]
+fn updating_deposit_and_withdrawal_fees() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            deposit_fee: Some(Decimal::permille(11)),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::GreaterThanMax("deposit_fee".to_string()));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            withdrawal_fee: Some(Decimal::permille(11)),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::GreaterThanMax("withdrawal_fee".to_string()));
+
+    update_config(
+        deps.as_mut(),
+        UpdateConfigMsg {
+            deposit_fee: Some(Decimal::percent(1)),
+            deposit_fee_source: Some(DepositFeeSource::Mint),
+            withdrawal_fee: Some(Decimal::permille(5)),
+            ..UpdateConfigMsg::default()
+        },
+    );
+
+    let fee_config = State::default().fee_config.load(deps.as_ref().storage).unwrap();
+    assert_eq!(fee_config.deposit_fee, Decimal::percent(1));
+    assert_eq!(fee_config.deposit_fee_source, DepositFeeSource::Mint);
+    assert_eq!(fee_config.withdrawal_fee, Decimal::permille(5));
+    assert_eq!(fee_config.protocol_reward_fee, Decimal::percent(1));
+}
+
+#[test]
+fn bonding_with_deposit_fee() {
+    let mut deps = setup_test();
+
+    split_protocol_fee(deps.as_mut());
+    update_config(
+        deps.as_mut(),
+        UpdateConfigMsg {
+            deposit_fee: Some(Decimal::percent(1)),
+            ..UpdateConfigMsg::default()
+        },
+    );
+
+    deps.querier.set_bank_balances(&[coin(12345 + 222, CONTRACT_DENOM)]);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[coin(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    // The 123 utoken fee is sent to the recipients, and the rest is staked: 12222 * 1000000 /
+    // 1025000 = 11923 ustake
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(Delegation::new("charlie", 12222).to_cosmos_msg()),
+            mint_msg("user_1", 11923),
+            SubMsg::new(SendFee::new(Addr::unchecked("treasury"), 73).to_cosmos_msg()),
+            SubMsg::new(SendFee::new(Addr::unchecked("insurance"), 50).to_cosmos_msg()),
+            check_received_coin(222),
+        ]
+    );
+
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/bonded")
+            .add_attribute("time", mock_env().block.time.seconds().to_string())
+            .add_attribute("height", mock_env().block.height.to_string())
+            .add_attribute("receiver", "user_1")
+            .add_attribute("token_bonded", "12345")
+            .add_attribute("utoken_buffered", "0")
+            .add_attribute("ustake_minted", "11923")
+            .add_attribute("utoken_deposit_fee", "123")
+            .add_attribute("ustake_deposit_fee", "0")]
+    );
+
+    // Donations are not charged
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[coin(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Donate {},
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg()),
+            check_received_coin(222),
+        ]
+    );
+}
+
+#[test]
+fn bonding_with_deposit_fee_taken_from_mint() {
+    let mut deps = setup_test();
+
+    split_protocol_fee(deps.as_mut());
+    update_config(
+        deps.as_mut(),
+        UpdateConfigMsg {
+            deposit_fee: Some(Decimal::percent(1)),
+            deposit_fee_source: Some(DepositFeeSource::Mint),
+            ..UpdateConfigMsg::default()
+        },
+    );
+
+    deps.querier.set_bank_balances(&[coin(12345 + 222, CONTRACT_DENOM)]);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[coin(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    // The full deposit is staked and 12043 ustake minted, of which 120 go to the recipients
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg()),
+            mint_msg("user_1", 11923),
+            mint_msg("treasury", 72),
+            mint_msg("insurance", 48),
+            check_received_coin(222),
+        ]
+    );
+
+    let event = res.events.iter().find(|event| event.ty == "erishub/bonded").unwrap();
+    assert!(event.attributes.iter().any(|a| a.key == "ustake_minted" && a.value == "12043"));
+    assert!(event.attributes.iter().any(|a| a.key == "utoken_deposit_fee" && a.value == "0"));
+    assert!(event.attributes.iter().any(|a| a.key == "ustake_deposit_fee" && a.value == "120"));
+}
+
+#[test]
+fn withdrawing_unbonded_with_fee() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    split_protocol_fee(deps.as_mut());
+    update_config(
+        deps.as_mut(),
+        UpdateConfigMsg {
+            withdrawal_fee: Some(Decimal::percent(1)),
+            ..UpdateConfigMsg::default()
+        },
+    );
+
+    state
+        .previous_batches
+        .save(
+            deps.as_mut().storage,
+            1,
+            &Batch {
+                id: 1,
+                reconciled: true,
+                total_shares: Uint128::new(20000),
+                utoken_unclaimed: Uint128::new(20400),
+                est_unbond_end_time: 10000,
+            },
+        )
+        .unwrap();
+
+    for user in ["user_1", "user_2"] {
+        state
+            .unbond_requests
+            .save(
+                deps.as_mut().storage,
+                (1, &Addr::unchecked(user)),
+                &UnbondRequest {
+                    id: 1,
+                    user: Addr::unchecked(user),
+                    shares: Uint128::new(10000),
+                },
+            )
+            .unwrap();
+    }
+    state
+        .auto_claims
+        .save(deps.as_mut().storage, (1, &Addr::unchecked("user_2")), &Default::default())
+        .unwrap();
+
+    // The withdrawable amount is reported after the fee: 10200 - 102
+    let res: WithdrawableResponse = query_helper_env(
+        deps.as_ref(),
+        QueryMsg::Withdrawable {
+            user: "user_1".to_string(),
+        },
+        20000,
+    );
+    assert_eq!(res.utoken, Uint128::new(10098));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            batch_ids: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "user_1".to_string(),
+                amount: vec![coin(10098, CONTRACT_DENOM)],
+            })),
+            SubMsg::new(SendFee::new(Addr::unchecked("treasury"), 61).to_cosmos_msg()),
+            SubMsg::new(SendFee::new(Addr::unchecked("insurance"), 41).to_cosmos_msg()),
+        ]
+    );
+
+    let event = res.events.iter().find(|event| event.ty == "erishub/unbonded_withdrawn").unwrap();
+    assert!(event.attributes.iter().any(|a| a.key == "utoken_refunded" && a.value == "10200"));
+    assert!(event.attributes.iter().any(|a| a.key == "utoken_withdrawal_fee" && a.value == "102"));
+
+    // Auto-claimed requests are charged the same fee
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("jake", &[]),
+        ExecuteMsg::DistributeUnbonded {
+            limit: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "user_2".to_string(),
+                amount: vec![coin(10098, CONTRACT_DENOM)],
+            })),
+            SubMsg::new(SendFee::new(Addr::unchecked("treasury"), 61).to_cosmos_msg()),
+            SubMsg::new(SendFee::new(Addr::unchecked("insurance"), 41).to_cosmos_msg()),
+        ]
+    );
+}
@@ -0,0 +1,407 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{
+    to_binary, Addr, Coin, CosmosMsg, Decimal, Deps, DepsMut, SubMsg, Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+
+use eris_staking::hub::{
+    DelegationStrategyConfig, ExecuteMsg, QueryMsg, ReceiveMsg, UpdateConfigMsg, UserVotes,
+    ValidatorVotesResponse,
+};
+
+use crate::constants::CONTRACT_DENOM;
+use crate::contract::execute;
+use crate::error::ContractError;
+use crate::state::State;
+use crate::types::Delegation;
+
+use super::helpers::{mock_env_at_timestamp, query_helper};
+use super::tests_default::{setup_test, STAKE_DENOM};
+
+fn query_validator_votes(deps: Deps) -> Vec<(String, Uint128)> {
+    let res: ValidatorVotesResponse = query_helper(deps, QueryMsg::ValidatorVotes {});
+    res.votes
+}
+
+fn votes(entries: &[(&str, u128)]) -> Vec<(String, Uint128)> {
+    entries.iter().map(|(v, amount)| (v.to_string(), Uint128::new(*amount))).collect()
+}
+
+fn lock(deps: DepsMut, user: &str, amount: u128) {
+    execute(
+        deps,
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: user.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&ReceiveMsg::LockVotingPower {}).unwrap(),
+        }),
+    )
+    .unwrap();
+}
+
+fn vote(deps: DepsMut, user: &str, votes: &[(&str, u16)]) {
+    execute(
+        deps,
+        mock_env(),
+        mock_info(user, &[]),
+        ExecuteMsg::Vote {
+            votes: votes.iter().map(|(v, bps)| (v.to_string(), *bps)).collect(),
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn voting() {
+    let mut deps = setup_test();
+
+    lock(deps.as_mut(), "user_1", 1000);
+    lock(deps.as_mut(), "user_2", 500);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::Vote {
+            votes: vec![("alice".to_string(), 6000), ("bob".to_string(), 4000)],
+        },
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_2", &[]),
+        ExecuteMsg::Vote {
+            votes: vec![("charlie".to_string(), 5000)],
+        },
+    )
+    .unwrap();
+
+    let validator_votes = query_validator_votes(deps.as_ref());
+    assert_eq!(validator_votes, votes(&[("alice", 600), ("bob", 400), ("charlie", 250)]));
+
+    let res: UserVotes = query_helper(
+        deps.as_ref(),
+        QueryMsg::UserVotes {
+            user: "user_1".to_string(),
+        },
+    );
+    assert_eq!(
+        res,
+        UserVotes {
+            user: Addr::unchecked("user_1"),
+            power: Uint128::new(1000),
+            votes: vec![("alice".to_string(), 6000), ("bob".to_string(), 4000)],
+        }
+    );
+
+    // Voting again replaces the previous votes
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::Vote {
+            votes: vec![("bob".to_string(), 10000)],
+        },
+    )
+    .unwrap();
+
+    let validator_votes = query_validator_votes(deps.as_ref());
+    assert_eq!(validator_votes, votes(&[("alice", 0), ("bob", 1000), ("charlie", 250)]));
+
+    // Voting with an empty list withdraws the votes
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_2", &[]),
+        ExecuteMsg::Vote {
+            votes: vec![],
+        },
+    )
+    .unwrap();
+
+    let validator_votes = query_validator_votes(deps.as_ref());
+    assert_eq!(validator_votes, votes(&[("alice", 0), ("bob", 1000), ("charlie", 0)]));
+
+    let res: UserVotes = query_helper(
+        deps.as_ref(),
+        QueryMsg::UserVotes {
+            user: "user_2".to_string(),
+        },
+    );
+    assert_eq!(
+        res,
+        UserVotes {
+            user: Addr::unchecked("user_2"),
+            power: Uint128::new(500),
+            votes: vec![],
+        }
+    );
+}
+
+#[test]
+fn voting_invalid_votes() {
+    let mut deps = setup_test();
+
+    let mut vote = |votes: Vec<(&str, u16)>| {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("user_1", &[]),
+            ExecuteMsg::Vote {
+                votes: votes.into_iter().map(|(v, bps)| (v.to_string(), bps)).collect(),
+            },
+        )
+        .unwrap_err()
+    };
+
+    assert_eq!(
+        vote(vec![("dave", 10000)]),
+        ContractError::ValidatorNotWhitelisted("dave".to_string())
+    );
+    assert_eq!(
+        vote(vec![("alice", 5000), ("alice", 5000)]),
+        ContractError::DuplicateVote("alice".to_string())
+    );
+    assert_eq!(
+        vote(vec![("alice", 6000), ("bob", 5000)]),
+        ContractError::VotesExceedMax {
+            max_bps: 10000,
+            total_bps: 11000,
+        }
+    );
+}
+
+#[test]
+fn locking_and_unlocking_voting_power() {
+    let mut deps = setup_test();
+
+    // Only Stake token can be locked
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("random_token", &[]),
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::LockVotingPower {}).unwrap(),
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidStakeToken("random_token".to_string()));
+
+    // Holding Stake token gives no voting power until it is locked in the hub
+    vote(deps.as_mut(), "user_1", &[("alice", 5000), ("bob", 5000)]);
+
+    let validator_votes = query_validator_votes(deps.as_ref());
+    assert_eq!(validator_votes, votes(&[("alice", 0), ("bob", 0), ("charlie", 0)]));
+
+    // Locking more updates the votes already cast
+    lock(deps.as_mut(), "user_1", 1000);
+    lock(deps.as_mut(), "user_1", 2000);
+
+    let validator_votes = query_validator_votes(deps.as_ref());
+    assert_eq!(validator_votes, votes(&[("alice", 1500), ("bob", 1500), ("charlie", 0)]));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::UnlockVotingPower {
+            amount: Uint128::new(3001),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::UnlockAmountTooHigh {
+            requested: Uint128::new(3001),
+            locked: Uint128::new(3000),
+        }
+    );
+
+    // Unlocked tokens are returned and no longer count, so they can't be voted with twice
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::UnlockVotingPower {
+            amount: Uint128::new(1000),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "user_1".to_string(),
+                amount: Uint128::new(1000),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+
+    let validator_votes = query_validator_votes(deps.as_ref());
+    assert_eq!(validator_votes, votes(&[("alice", 1000), ("bob", 1000), ("charlie", 0)]));
+
+    let res: UserVotes = query_helper(
+        deps.as_ref(),
+        QueryMsg::UserVotes {
+            user: "user_1".to_string(),
+        },
+    );
+    assert_eq!(res.power, Uint128::new(2000));
+}
+
+#[test]
+fn removing_validators_with_votes() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+
+    lock(deps.as_mut(), "user_1", 1000);
+    lock(deps.as_mut(), "user_2", 2000);
+    vote(deps.as_mut(), "user_1", &[("alice", 5000), ("charlie", 5000)]);
+    vote(deps.as_mut(), "user_2", &[("charlie", 10000)]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap();
+
+    let state = State::default();
+    let charlie_votes =
+        state.gauge_validator_votes.may_load(deps.as_ref().storage, "charlie").unwrap();
+    assert_eq!(charlie_votes, None);
+
+    // The votes for the removed validator are dropped once the user's voting power changes
+    lock(deps.as_mut(), "user_1", 1000);
+
+    let res: UserVotes = query_helper(
+        deps.as_ref(),
+        QueryMsg::UserVotes {
+            user: "user_1".to_string(),
+        },
+    );
+    assert_eq!(res.votes, vec![("alice".to_string(), 5000)]);
+
+    // Once added back, the validator only receives the votes cast since
+    deps.querier.set_staking_validators(&[("charlie", Decimal::percent(5))]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "charlie".to_string(),
+            weight: None,
+        },
+    )
+    .unwrap();
+
+    vote(deps.as_mut(), "user_1", &[("charlie", 10000)]);
+    lock(deps.as_mut(), "user_2", 1000);
+
+    let validator_votes = query_validator_votes(deps.as_ref());
+    assert_eq!(validator_votes, votes(&[("alice", 0), ("bob", 0), ("charlie", 2000)]));
+}
+
+#[test]
+fn tallying_votes() {
+    let mut deps = setup_test();
+
+    lock(deps.as_mut(), "user_1", 3000);
+    lock(deps.as_mut(), "user_2", 1000);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::Vote {
+            votes: vec![("bob".to_string(), 10000)],
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_2", &[]),
+        ExecuteMsg::Vote {
+            votes: vec![("charlie".to_string(), 10000)],
+        },
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(300000),
+        mock_info("jake", &[]),
+        ExecuteMsg::TallyVotes {},
+    )
+    .unwrap();
+
+    let res: ValidatorVotesResponse = query_helper(deps.as_ref(), QueryMsg::ValidatorVotes {});
+    assert_eq!(res.last_tally_time, 300000);
+    assert_eq!(
+        res.tallied_weights,
+        vec![("alice".to_string(), 0), ("bob".to_string(), 7500), ("charlie".to_string(), 2500)]
+    );
+
+    // Votes can only be tallied once per epoch
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(400000),
+        mock_info("jake", &[]),
+        ExecuteMsg::TallyVotes {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::TallyNotReady(559200));
+
+    // Once the gauge strategy is enabled, new delegations follow the tallied weights
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            delegation_strategy: Some(DelegationStrategyConfig::Gauges),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap();
+
+    deps.querier.set_bank_balances(&[Coin::new(12345, CONTRACT_DENOM)]);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 0),
+        Delegation::new("bob", 300000),
+        Delegation::new("charlie", 50000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 350000);
+
+    // Alice received no votes, so she receives nothing even though her delegation is the smallest
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_3", &[Coin::new(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg()));
+}
@@ -0,0 +1,281 @@
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{coin, to_binary, DepsMut, Event, Uint128};
+use cw20::Cw20ReceiveMsg;
+
+use eris_staking::hub::{ConfigResponse, ExecuteMsg, PauseState, QueryMsg, ReceiveMsg, Role};
+
+use crate::constants::CONTRACT_DENOM;
+use crate::contract::execute;
+use crate::error::ContractError;
+use crate::types::Delegation;
+
+use super::helpers::query_helper;
+use super::tests_default::{setup_test, STAKE_DENOM};
+
+fn grant_guardian(deps: DepsMut, account: &str) {
+    execute(
+        deps,
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::GrantRole {
+            role: Role::Guardian,
+            account: account.to_string(),
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn pausing_and_unpausing() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Pause(PauseState {
+            bond: true,
+            ..PauseState::default()
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::UnauthorizedRole(Role::Guardian));
+
+    grant_guardian(deps.as_mut(), "jake");
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Pause(PauseState {
+            bond: true,
+            withdraw: true,
+            ..PauseState::default()
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/paused")
+            .add_attribute("bond", "true")
+            .add_attribute("unbond", "false")
+            .add_attribute("withdraw", "true")
+            .add_attribute("harvest", "false")]
+    );
+
+    // Flags that are not set are left as they are
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Pause(PauseState {
+            harvest: true,
+            ..PauseState::default()
+        }),
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(
+        res.paused,
+        PauseState {
+            bond: true,
+            unbond: false,
+            withdraw: true,
+            harvest: true,
+        }
+    );
+
+    // Only the owner can lift the pause
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Unpause(PauseState {
+            bond: true,
+            ..PauseState::default()
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::Unpause(PauseState {
+            bond: true,
+            harvest: true,
+            ..PauseState::default()
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/unpaused")
+            .add_attribute("bond", "false")
+            .add_attribute("unbond", "false")
+            .add_attribute("withdraw", "true")
+            .add_attribute("harvest", "false")]
+    );
+}
+
+#[test]
+fn enforcing_pause() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::Pause(PauseState {
+            bond: true,
+            unbond: true,
+            withdraw: true,
+            harvest: true,
+        }),
+    )
+    .unwrap();
+
+    let paused_msgs = vec![
+        (
+            mock_info("user_1", &[coin(12345, CONTRACT_DENOM)]),
+            ExecuteMsg::Bond {
+                receiver: None,
+            },
+            ContractError::Paused("bonding".to_string()),
+        ),
+        (
+            mock_info("user_1", &[coin(12345, CONTRACT_DENOM)]),
+            ExecuteMsg::Donate {},
+            ContractError::Paused("bonding".to_string()),
+        ),
+        (
+            mock_info(STAKE_DENOM, &[]),
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "user_1".to_string(),
+                amount: Uint128::new(1000),
+                msg: to_binary(&ReceiveMsg::QueueUnbond {
+                    receiver: None,
+                    auto_claim: None,
+                })
+                .unwrap(),
+            }),
+            ContractError::Paused("unbonding".to_string()),
+        ),
+        (
+            mock_info("user_1", &[]),
+            ExecuteMsg::SubmitBatch {
+                force: None,
+            },
+            ContractError::Paused("unbonding".to_string()),
+        ),
+        (
+            mock_info("user_1", &[]),
+            ExecuteMsg::WithdrawUnbonded {
+                receiver: None,
+                batch_ids: None,
+                limit: None,
+            },
+            ContractError::Paused("withdrawing".to_string()),
+        ),
+        (
+            mock_info("user_1", &[]),
+            ExecuteMsg::DistributeUnbonded {
+                limit: None,
+            },
+            ContractError::Paused("withdrawing".to_string()),
+        ),
+        (
+            mock_info(MOCK_CONTRACT_ADDR, &[]),
+            ExecuteMsg::Harvest {},
+            ContractError::Paused("harvesting".to_string()),
+        ),
+    ];
+
+    for (info, msg, expected) in paused_msgs {
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, expected);
+    }
+
+    // Other operations are not affected
+    execute(deps.as_mut(), mock_env(), mock_info("jake", &[]), ExecuteMsg::Reconcile {}).unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::Unpause(PauseState {
+            bond: true,
+            ..PauseState::default()
+        }),
+    )
+    .unwrap();
+
+    deps.querier.set_bank_balances(&[coin(12345, CONTRACT_DENOM)]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[coin(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn locking_voting_power_while_unbonding_paused() {
+    let mut deps = setup_test();
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::Pause(PauseState {
+            unbond: true,
+            ..PauseState::default()
+        }),
+    )
+    .unwrap();
+
+    let receive_msg = |msg: ReceiveMsg| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(1000),
+            msg: to_binary(&msg).unwrap(),
+        })
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        receive_msg(ReceiveMsg::InstantUnbond {
+            receiver: None,
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Paused("unbonding".to_string()));
+
+    // Locking voting power is not an unbonding, so it goes through
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        receive_msg(ReceiveMsg::LockVotingPower {}),
+    )
+    .unwrap();
+}
@@ -0,0 +1,355 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{Addr, Deps, DepsMut, Event, Uint128};
+
+use eris_staking::hub::{
+    ExecuteMsg, PendingBatch, QueryMsg, RedelegationResponseItem, Role, UpdateConfigMsg,
+};
+
+use crate::contract::execute;
+use crate::error::ContractError;
+use crate::state::State;
+use crate::types::{Delegation, Redelegation};
+
+use super::helpers::{mock_env_at_timestamp, query_helper};
+use super::tests_default::{setup_test, STAKE_DENOM};
+
+fn grant_operator(deps: DepsMut, account: &str) {
+    execute(
+        deps,
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::GrantRole {
+            role: Role::Operator,
+            account: account.to_string(),
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn granting_and_revoking_roles() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::GrantRole {
+            role: Role::Operator,
+            account: "jake".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::GrantRole {
+            role: Role::Operator,
+            account: "pumpkin".to_string(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/role_granted")
+            .add_attribute("role", "operator")
+            .add_attribute("account", "pumpkin")]
+    );
+
+    grant_operator(deps.as_mut(), "jake");
+
+    let res: Vec<String> = query_helper(
+        deps.as_ref(),
+        QueryMsg::RoleHolders {
+            role: Role::Operator,
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec!["jake".to_string(), "pumpkin".to_string()]);
+
+    let res: Vec<String> = query_helper(
+        deps.as_ref(),
+        QueryMsg::RoleHolders {
+            role: Role::Operator,
+            start_after: Some("jake".to_string()),
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec!["pumpkin".to_string()]);
+
+    // Operators can not grant or revoke roles themselves
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::RevokeRole {
+            role: Role::Operator,
+            account: "pumpkin".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RevokeRole {
+            role: Role::Operator,
+            account: "pumpkin".to_string(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/role_revoked")
+            .add_attribute("role", "operator")
+            .add_attribute("account", "pumpkin")]
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RevokeRole {
+            role: Role::Operator,
+            account: "pumpkin".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::RoleNotHeld {
+            account: "pumpkin".to_string(),
+            role: Role::Operator,
+        }
+    );
+
+    let state = State::default();
+    assert!(state
+        .has_role(deps.as_ref().storage, &Addr::unchecked("jake"), Role::Operator)
+        .unwrap());
+    assert!(!state
+        .has_role(deps.as_ref().storage, &Addr::unchecked("pumpkin"), Role::Operator)
+        .unwrap());
+}
+
+#[test]
+fn force_submitting_batch() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782),
+        Delegation::new("bob", 345782),
+        Delegation::new("charlie", 345781),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1012043);
+
+    state
+        .pending_batch
+        .save(
+            deps.as_mut().storage,
+            &PendingBatch {
+                id: 1,
+                ustake_to_burn: Uint128::new(92876),
+                est_unbond_start_time: 269200,
+            },
+        )
+        .unwrap();
+
+    // Without `force`, the batch can not be submitted early, even by an operator
+    grant_operator(deps.as_mut(), "jake");
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(100000),
+        mock_info("jake", &[]),
+        ExecuteMsg::SubmitBatch {
+            force: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::BatchNotReady(269200));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(100000),
+        mock_info("pumpkin", &[]),
+        ExecuteMsg::SubmitBatch {
+            force: Some(true),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::UnauthorizedRole(Role::Operator));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(100000),
+        mock_info("jake", &[]),
+        ExecuteMsg::SubmitBatch {
+            force: Some(true),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 5);
+
+    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
+    assert_eq!(pending_batch.id, 2);
+}
+
+#[test]
+fn force_rebalancing() {
+    let mut deps = setup_test();
+
+    // Target: (1300 + 900 + 800) / 3 = 1000
+    // Deviations: alice +300, bob -100, charlie -200
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 1300),
+        Delegation::new("bob", 900),
+        Delegation::new("charlie", 800),
+    ]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            min_redelegation: Some(Uint128::new(500)),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap();
+
+    let simulate = |deps: Deps, force: Option<bool>| {
+        query_helper::<Vec<RedelegationResponseItem>>(
+            deps,
+            QueryMsg::SimulateRebalance {
+                min_deviation: Some(Uint128::new(150)),
+                max_redelegations: None,
+                force,
+            },
+        )
+        .into_iter()
+        .map(|rd| Redelegation::new(&rd.src, &rd.dst, rd.amount.u128()))
+        .collect::<Vec<_>>()
+    };
+
+    // The configured minimum of 500 applies unless forced
+    assert_eq!(simulate(deps.as_ref(), None), vec![]);
+    assert_eq!(
+        simulate(deps.as_ref(), Some(true)),
+        vec![Redelegation::new("alice", "charlie", 200)]
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Rebalance {
+            min_deviation: Some(Uint128::new(150)),
+            max_redelegations: None,
+            force: Some(true),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::UnauthorizedRole(Role::Operator));
+
+    grant_operator(deps.as_mut(), "jake");
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Rebalance {
+            min_deviation: Some(Uint128::new(150)),
+            max_redelegations: None,
+            force: Some(true),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[0].msg, Redelegation::new("alice", "charlie", 200).to_cosmos_msg());
+}
+
+#[test]
+fn operator_removing_validator() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateTimelockDelay {
+            delay: 86400,
+        },
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    grant_operator(deps.as_mut(), "jake");
+
+    // Operators can't bypass the timelock; unhealthy validators are removed with `check_validators`
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "bob".to_string(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages, vec![]);
+    assert_eq!(res.events[0].ty, "erishub/change_proposed");
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        validators,
+        vec![String::from("alice"), String::from("bob"), String::from("charlie")]
+    );
+}
@@ -0,0 +1,172 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{coin, Decimal, DepsMut, Response, StdError, Uint128};
+
+use eris_staking::hub::{
+    DepositFeeSource, ExecuteMsg, QueryMsg, SimulateBondResponse, SimulateUnbondResponse,
+    UpdateConfigMsg,
+};
+
+use crate::constants::CONTRACT_DENOM;
+use crate::contract::{execute, query};
+use crate::error::ContractError;
+use crate::types::Delegation;
+
+use super::helpers::{query_helper, query_helper_env, update_config};
+use super::tests_default::{setup_test, STAKE_DENOM};
+
+fn event_attribute(res: &Response, key: &str) -> Uint128 {
+    let attr = res.events[0].attributes.iter().find(|attr| attr.key == key).unwrap();
+    attr.value.parse().unwrap()
+}
+
+/// The simulation should give the same amounts as actually bonding
+fn assert_simulated_bond(deps: DepsMut, expected: SimulateBondResponse) {
+    let res: SimulateBondResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::SimulateBond {
+            amount: Uint128::new(12345),
+        },
+    );
+    assert_eq!(res, expected);
+
+    let res = execute(
+        deps,
+        mock_env(),
+        mock_info("user_1", &[coin(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    let ustake_minted = event_attribute(&res, "ustake_minted");
+    let ustake_deposit_fee = event_attribute(&res, "ustake_deposit_fee");
+    assert_eq!(ustake_minted - ustake_deposit_fee, expected.ustake_to_receive);
+    assert_eq!(event_attribute(&res, "utoken_deposit_fee"), expected.utoken_deposit_fee);
+    assert_eq!(ustake_deposit_fee, expected.ustake_deposit_fee);
+}
+
+#[test]
+fn simulating_bond() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+    deps.querier.set_bank_balances(&[coin(12345, CONTRACT_DENOM)]);
+
+    // 1000000 * 12345 / 1025000 = 12043
+    assert_simulated_bond(
+        deps.as_mut(),
+        SimulateBondResponse {
+            ustake_to_receive: Uint128::new(12043),
+            utoken_deposit_fee: Uint128::zero(),
+            ustake_deposit_fee: Uint128::zero(),
+        },
+    );
+
+    // 1000000 * (12345 - 123) / 1025000 = 11923
+    update_config(
+        deps.as_mut(),
+        UpdateConfigMsg {
+            deposit_fee: Some(Decimal::percent(1)),
+            ..UpdateConfigMsg::default()
+        },
+    );
+    assert_simulated_bond(
+        deps.as_mut(),
+        SimulateBondResponse {
+            ustake_to_receive: Uint128::new(11923),
+            utoken_deposit_fee: Uint128::new(123),
+            ustake_deposit_fee: Uint128::zero(),
+        },
+    );
+
+    // 12043 - 12043 * 0.01 = 11923
+    update_config(
+        deps.as_mut(),
+        UpdateConfigMsg {
+            deposit_fee_source: Some(DepositFeeSource::Mint),
+            ..UpdateConfigMsg::default()
+        },
+    );
+    assert_simulated_bond(
+        deps.as_mut(),
+        SimulateBondResponse {
+            ustake_to_receive: Uint128::new(11923),
+            utoken_deposit_fee: Uint128::zero(),
+            ustake_deposit_fee: Uint128::new(120),
+        },
+    );
+}
+
+#[test]
+fn simulating_unbond() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    update_config(
+        deps.as_mut(),
+        UpdateConfigMsg {
+            withdrawal_fee: Some(Decimal::permille(5)),
+            ..UpdateConfigMsg::default()
+        },
+    );
+
+    // 1025000 * 100000 / 1000000 = 102500, of which 512 are taken as withdrawal fee. The pending
+    // batch is due at 269200, and finishes unbonding 21 days later
+    let res: SimulateUnbondResponse = query_helper_env(
+        deps.as_ref(),
+        QueryMsg::SimulateUnbond {
+            ustake: Uint128::new(100000),
+        },
+        20000,
+    );
+    assert_eq!(
+        res,
+        SimulateUnbondResponse {
+            utoken_to_receive: Uint128::new(101988),
+            utoken_withdrawal_fee: Uint128::new(512),
+            batch_id: 1,
+            est_unbond_start_time: 269200,
+            est_unbond_end_time: 269200 + 1814400,
+        }
+    );
+
+    // An overdue batch can be submitted right away
+    let res: SimulateUnbondResponse = query_helper_env(
+        deps.as_ref(),
+        QueryMsg::SimulateUnbond {
+            ustake: Uint128::new(100000),
+        },
+        300000,
+    );
+    assert_eq!(res.est_unbond_start_time, 300000);
+    assert_eq!(res.est_unbond_end_time, 300000 + 1814400);
+
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::SimulateUnbond {
+            ustake: Uint128::new(1000001),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::from(ContractError::ExceedsTotalSupply {
+            ustake: Uint128::new(1000001),
+            ustake_supply: Uint128::new(1000000),
+        })
+    );
+}
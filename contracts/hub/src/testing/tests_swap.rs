@@ -0,0 +1,389 @@
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    coin, to_binary, CosmosMsg, Decimal, DepsMut, Event, Reply, SubMsg, SubMsgResult, Uint128,
+    WasmMsg,
+};
+
+use eris_staking::hub::{
+    CallbackMsg, ExecuteMsg, QueryMsg, RouterExecuteMsg, SwapOperation, SwapRoute,
+};
+
+use crate::constants::CONTRACT_DENOM;
+use crate::contract::{execute, reply};
+use crate::error::ContractError;
+use crate::types::Delegation;
+
+use super::helpers::query_helper;
+use super::tests_default::{check_received_coin, setup_test, STAKE_DENOM};
+
+fn swap_route(denom: &str, hops: &[&str]) -> SwapRoute {
+    let mut denoms = vec![denom];
+    denoms.extend_from_slice(hops);
+
+    SwapRoute {
+        denom: denom.to_string(),
+        router: "router".to_string(),
+        operations: denoms
+            .windows(2)
+            .map(|pair| SwapOperation {
+                offer_denom: pair[0].to_string(),
+                ask_denom: pair[1].to_string(),
+            })
+            .collect(),
+        max_spread: Decimal::percent(1),
+        min_price: Decimal::percent(200),
+    }
+}
+
+fn update_swap_routes(deps: DepsMut, add: Vec<SwapRoute>, remove: Vec<String>) {
+    execute(
+        deps,
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateSwapRoutes {
+            add,
+            remove,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn updating_swap_routes() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::UpdateSwapRoutes {
+            add: vec![swap_route("uatom", &[CONTRACT_DENOM])],
+            remove: vec![],
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let invalid_routes = vec![
+        (
+            swap_route(CONTRACT_DENOM, &["uatom", CONTRACT_DENOM]),
+            ContractError::CannotSwapContractDenom(CONTRACT_DENOM.to_string()),
+        ),
+        (swap_route("uatom", &[]), ContractError::InvalidSwapRoute("uatom".to_string())),
+        (swap_route("uatom", &["uosmo"]), ContractError::InvalidSwapRoute("uatom".to_string())),
+        (
+            SwapRoute {
+                operations: vec![SwapOperation {
+                    offer_denom: "uosmo".to_string(),
+                    ask_denom: CONTRACT_DENOM.to_string(),
+                }],
+                ..swap_route("uatom", &[CONTRACT_DENOM])
+            },
+            ContractError::InvalidSwapRoute("uatom".to_string()),
+        ),
+        (
+            SwapRoute {
+                max_spread: Decimal::percent(101),
+                ..swap_route("uatom", &[CONTRACT_DENOM])
+            },
+            ContractError::GreaterThanOne("max_spread".to_string()),
+        ),
+        (
+            SwapRoute {
+                min_price: Decimal::zero(),
+                ..swap_route("uatom", &[CONTRACT_DENOM])
+            },
+            ContractError::ZeroSwapMinPrice("uatom".to_string()),
+        ),
+    ];
+
+    for (route, expected) in invalid_routes {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateSwapRoutes {
+                add: vec![route],
+                remove: vec![],
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(err, expected);
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateSwapRoutes {
+            add: vec![
+                swap_route("uatom", &[CONTRACT_DENOM]),
+                swap_route("ustars", &["uosmo", CONTRACT_DENOM]),
+            ],
+            remove: vec![],
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/swap_routes_updated").add_attribute("denoms", "uatom,ustars")]
+    );
+
+    // Routes are replaced by denom, and removals are applied before additions
+    update_swap_routes(
+        deps.as_mut(),
+        vec![SwapRoute {
+            max_spread: Decimal::percent(3),
+            ..swap_route("ustars", &[CONTRACT_DENOM])
+        }],
+        vec!["uatom".to_string(), "ustars".to_string()],
+    );
+
+    let res: Vec<SwapRoute> = query_helper(deps.as_ref(), QueryMsg::SwapRoutes {});
+    assert_eq!(
+        res,
+        vec![SwapRoute {
+            max_spread: Decimal::percent(3),
+            ..swap_route("ustars", &[CONTRACT_DENOM])
+        }]
+    );
+}
+
+#[test]
+fn harvesting_with_swap_routes() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    update_swap_routes(deps.as_mut(), vec![swap_route("uatom", &[CONTRACT_DENOM])], vec![]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+
+    // The swaps happen after the rewards are withdrawn, and before the Token received is checked
+    assert_eq!(res.messages.len(), 6);
+    assert_eq!(
+        res.messages[3],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::Callback(CallbackMsg::Swap {})).unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(res.messages[4], check_received_coin(0));
+    assert_eq!(
+        res.messages[5],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::Callback(CallbackMsg::Reinvest {})).unwrap(),
+            funds: vec![]
+        }))
+    );
+}
+
+#[test]
+fn swapping() {
+    let mut deps = setup_test();
+
+    update_swap_routes(
+        deps.as_mut(),
+        vec![
+            swap_route("uatom", &[CONTRACT_DENOM]),
+            swap_route("ustars", &["uosmo", CONTRACT_DENOM]),
+            swap_route("uluna", &[CONTRACT_DENOM]),
+        ],
+        vec![],
+    );
+
+    deps.querier.swap_rate = Decimal::percent(250);
+    deps.querier.set_bank_balances(&[
+        coin(1000, "uatom"),
+        coin(200, "ustars"),
+        coin(500, "uusd"),
+        coin(300, CONTRACT_DENOM),
+    ]);
+
+    // Only the callback itself can swap
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Callback(CallbackMsg::Swap {}),
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::UnauthorizedCallback);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Swap {}),
+    )
+    .unwrap();
+
+    // Denoms without a balance or without a route are left as they are. The router simulates
+    // 2500 and 500 utoken, so the minimum received is 99% of those
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::reply_on_error(
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: "router".to_string(),
+                    msg: to_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                        operations: swap_route("uatom", &[CONTRACT_DENOM]).operations,
+                        minimum_receive: Some(Uint128::new(2475)),
+                    })
+                    .unwrap(),
+                    funds: vec![coin(1000, "uatom")],
+                }),
+                2
+            ),
+            SubMsg::reply_on_error(
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: "router".to_string(),
+                    msg: to_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                        operations: swap_route("ustars", &["uosmo", CONTRACT_DENOM]).operations,
+                        minimum_receive: Some(Uint128::new(495)),
+                    })
+                    .unwrap(),
+                    funds: vec![coin(200, "ustars")],
+                }),
+                2
+            ),
+        ]
+    );
+
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/swapped")
+            .add_attribute("offer_coin", "1000uatom")
+            .add_attribute("minimum_receive", 2475.to_string() + CONTRACT_DENOM)
+            .add_attribute("offer_coin", "200ustars")
+            .add_attribute("minimum_receive", 495.to_string() + CONTRACT_DENOM)]
+    );
+}
+
+#[test]
+fn swapping_with_failing_router() {
+    let mut deps = setup_test();
+
+    update_swap_routes(
+        deps.as_mut(),
+        vec![
+            SwapRoute {
+                router: "broken_router".to_string(),
+                ..swap_route("uatom", &[CONTRACT_DENOM])
+            },
+            swap_route("ustars", &[CONTRACT_DENOM]),
+        ],
+        vec![],
+    );
+
+    deps.querier.swap_rate = Decimal::percent(250);
+    deps.querier.failing_routers = vec!["broken_router".to_string()];
+    deps.querier.set_bank_balances(&[coin(1000, "uatom"), coin(200, "ustars")]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Swap {}),
+    )
+    .unwrap();
+
+    // The route whose simulation fails is skipped, and the other one is still swapped
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "router".to_string(),
+                msg: to_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                    operations: swap_route("ustars", &[CONTRACT_DENOM]).operations,
+                    minimum_receive: Some(Uint128::new(495)),
+                })
+                .unwrap(),
+                funds: vec![coin(200, "ustars")],
+            }),
+            2
+        )]
+    );
+
+    let attributes = &res.events[0].attributes;
+    assert_eq!(attributes[0].key, "simulation_failed");
+    assert!(attributes[0].value.starts_with("uatom: "));
+    assert_eq!(attributes[1].value, "200ustars");
+}
+
+#[test]
+fn swapping_after_price_manipulation() {
+    let mut deps = setup_test();
+
+    update_swap_routes(deps.as_mut(), vec![swap_route("uatom", &[CONTRACT_DENOM])], vec![]);
+
+    // A trader dumps uatom into the router's pool right before triggering the harvest, so that the
+    // router simulates only 100 utoken for the hub's 1000 uatom
+    deps.querier.swap_rate = Decimal::percent(10);
+    deps.querier.set_bank_balances(&[coin(1000, "uatom")]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Swap {}),
+    )
+    .unwrap();
+
+    // The minimum received stays at the configured price of 2 utoken per uatom, so the swap fails
+    // and is skipped instead of being executed at the manipulated price
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "router".to_string(),
+                msg: to_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                    operations: swap_route("uatom", &[CONTRACT_DENOM]).operations,
+                    minimum_receive: Some(Uint128::new(2000)),
+                })
+                .unwrap(),
+                funds: vec![coin(1000, "uatom")],
+            }),
+            2
+        )]
+    );
+}
+
+#[test]
+fn skipping_failed_swaps() {
+    let mut deps = setup_test();
+
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 2,
+            result: SubMsgResult::Err("assertion failed; minimum receive amount".to_string()),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages, vec![]);
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/swap_failed")
+            .add_attribute("error", "assertion failed; minimum receive amount")]
+    );
+}
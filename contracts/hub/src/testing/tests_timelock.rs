@@ -0,0 +1,414 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{Decimal, Deps, DepsMut, Event, Uint128};
+
+use eris_staking::hub::{
+    ConfigChange, ConfigResponse, ExecuteMsg, LiquidityBufferConfig, PendingChange, QueryMsg,
+    UpdateConfigMsg,
+};
+
+use crate::contract::execute;
+use crate::error::ContractError;
+use crate::state::State;
+
+use super::helpers::{mock_env_at_timestamp, query_helper};
+use super::tests_default::setup_test;
+
+fn set_timelock_delay(deps: DepsMut, delay: u64) {
+    execute(
+        deps,
+        mock_env_at_timestamp(10000),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateTimelockDelay {
+            delay,
+        },
+    )
+    .unwrap();
+}
+
+fn query_pending_changes(deps: Deps) -> Vec<PendingChange> {
+    query_helper(
+        deps,
+        QueryMsg::PendingChanges {
+            start_after: None,
+            limit: None,
+        },
+    )
+}
+
+#[test]
+fn applying_changes_without_delay() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::UpdateTimelockDelay {
+            delay: 86400,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    // Without a delay, changes are applied right away
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateTimelockDelay {
+            delay: 86400,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/timelock_delay_updated").add_attribute("delay", "86400")]
+    );
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.timelock_delay, 86400);
+    assert_eq!(query_pending_changes(deps.as_ref()), vec![]);
+}
+
+#[test]
+fn queuing_changes() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    set_timelock_delay(deps.as_mut(), 86400);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            protocol_reward_fee: Some(Decimal::percent(5)),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/change_proposed")
+            .add_attribute("id", "1")
+            .add_attribute("executable_at", "106400")]
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(30000),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+            weight: None,
+        },
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(30000),
+        mock_info("owner", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap();
+
+    // Nothing has changed yet
+    let fee_config = state.fee_config.load(deps.as_ref().storage).unwrap();
+    assert_eq!(fee_config.protocol_reward_fee, Decimal::percent(1));
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(validators, vec!["alice".to_string(), "bob".to_string(), "charlie".to_string()]);
+
+    assert_eq!(
+        query_pending_changes(deps.as_ref()),
+        vec![
+            PendingChange {
+                id: 1,
+                change: ConfigChange::UpdateConfig(UpdateConfigMsg {
+                    protocol_reward_fee: Some(Decimal::percent(5)),
+                    ..UpdateConfigMsg::default()
+                }),
+                executable_at: 106400,
+            },
+            PendingChange {
+                id: 2,
+                change: ConfigChange::AddValidator {
+                    validator: "dave".to_string(),
+                    weight: None,
+                },
+                executable_at: 116400,
+            },
+            PendingChange {
+                id: 3,
+                change: ConfigChange::RemoveValidator {
+                    validator: "charlie".to_string(),
+                },
+                executable_at: 116400,
+            },
+        ]
+    );
+
+    let res: Vec<PendingChange> = query_helper(
+        deps.as_ref(),
+        QueryMsg::PendingChanges {
+            start_after: Some(1),
+            limit: Some(1),
+        },
+    );
+    assert_eq!(res.iter().map(|change| change.id).collect::<Vec<_>>(), vec![2]);
+}
+
+#[test]
+fn executing_and_cancelling_changes() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    set_timelock_delay(deps.as_mut(), 86400);
+
+    for change in [
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            protocol_reward_fee: Some(Decimal::percent(5)),
+            ..UpdateConfigMsg::default()
+        }),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+            weight: None,
+        },
+        // Lowering the delay is itself subject to the current delay
+        ExecuteMsg::UpdateTimelockDelay {
+            delay: 0,
+        },
+    ] {
+        execute(deps.as_mut(), mock_env_at_timestamp(20000), mock_info("owner", &[]), change)
+            .unwrap();
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(106399),
+        mock_info("owner", &[]),
+        ExecuteMsg::ExecutePendingChange {
+            id: 1,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::PendingChangeNotReady {
+            id: 1,
+            executable_at: 106400,
+        }
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(106400),
+        mock_info("jake", &[]),
+        ExecuteMsg::ExecutePendingChange {
+            id: 1,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(106400),
+        mock_info("owner", &[]),
+        ExecuteMsg::ExecutePendingChange {
+            id: 1,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.events, vec![Event::new("erishub/change_executed").add_attribute("id", "1")]);
+
+    let fee_config = state.fee_config.load(deps.as_ref().storage).unwrap();
+    assert_eq!(fee_config.protocol_reward_fee, Decimal::percent(5));
+
+    // A change can only be executed once
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(106400),
+        mock_info("owner", &[]),
+        ExecuteMsg::ExecutePendingChange {
+            id: 1,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::PendingChangeNotFound(1));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::CancelPendingChange {
+            id: 2,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::CancelPendingChange {
+            id: 2,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.events, vec![Event::new("erishub/change_cancelled").add_attribute("id", "2")]);
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(validators, vec!["alice".to_string(), "bob".to_string(), "charlie".to_string()]);
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(106400),
+        mock_info("owner", &[]),
+        ExecuteMsg::ExecutePendingChange {
+            id: 3,
+        },
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.timelock_delay, 0);
+    assert_eq!(query_pending_changes(deps.as_ref()), vec![]);
+}
+
+#[test]
+fn queuing_weight_and_buffer_updates() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    set_timelock_delay(deps.as_mut(), 86400);
+
+    let buffer_config = LiquidityBufferConfig {
+        target: Uint128::new(1000),
+        bond_share: Decimal::percent(20),
+        reward_share: Decimal::percent(50),
+        instant_unbond_fee: Decimal::percent(1),
+    };
+
+    for change in [
+        ExecuteMsg::UpdateValidatorWeights {
+            weights: vec![("alice".to_string(), 20)],
+        },
+        ExecuteMsg::UpdateLiquidityBuffer {
+            config: buffer_config.clone(),
+        },
+    ] {
+        execute(deps.as_mut(), mock_env_at_timestamp(20000), mock_info("owner", &[]), change)
+            .unwrap();
+    }
+
+    // Nothing has changed yet
+    let alice = ["alice".to_string()];
+    let weights = state.load_validator_weights(deps.as_ref().storage, &alice).unwrap();
+    assert_eq!(weights, vec![100]);
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.liquidity_buffer, LiquidityBufferConfig::default());
+
+    for id in [1, 2] {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(106400),
+            mock_info("owner", &[]),
+            ExecuteMsg::ExecutePendingChange {
+                id,
+            },
+        )
+        .unwrap();
+    }
+
+    let weights = state.load_validator_weights(deps.as_ref().storage, &alice).unwrap();
+    assert_eq!(weights, vec![20]);
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.liquidity_buffer, buffer_config);
+}
+
+#[test]
+fn validating_changes_when_proposed() {
+    let mut deps = setup_test();
+
+    set_timelock_delay(deps.as_mut(), 86400);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig(UpdateConfigMsg {
+            withdrawal_fee: Some(Decimal::percent(50)),
+            ..UpdateConfigMsg::default()
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::GreaterThanMax("withdrawal_fee".to_string()));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "alice".to_string(),
+            weight: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ValidatorAlreadyWhitelisted("alice".to_string()));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ValidatorNotWhitelisted("dave".to_string()));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateValidatorWeights {
+            weights: vec![("dave".to_string(), 20)],
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ValidatorNotWhitelisted("dave".to_string()));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateLiquidityBuffer {
+            config: LiquidityBufferConfig {
+                reward_share: Decimal::percent(101),
+                ..LiquidityBufferConfig::default()
+            },
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidBufferShares);
+
+    assert_eq!(query_pending_changes(deps.as_ref()), vec![]);
+}
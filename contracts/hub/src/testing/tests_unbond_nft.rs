@@ -0,0 +1,667 @@
+use cosmwasm_std::testing::{mock_info, MockApi, MockStorage};
+use cosmwasm_std::{
+    to_binary, Addr, BankMsg, Coin, CosmosMsg, OwnedDeps, SubMsg, Timestamp, Uint128, WasmMsg,
+};
+
+use cw20::Expiration;
+use eris_staking::hub::{
+    Approval, Batch, ContractInfoResponse, Cw721ReceiveMsg, ExecuteMsg, NftInfoResponse,
+    NumTokensResponse, OwnerOfResponse, PendingBatch, QueryMsg, TokensResponse, UnbondRequest,
+    UnbondRequestsByBatchResponseItem, UnbondRequestsByUserResponseItem, WithdrawableResponse,
+};
+
+use serde::Serialize;
+
+use crate::constants::CONTRACT_DENOM;
+use crate::contract::execute;
+use crate::error::ContractError;
+use crate::state::State;
+
+use super::custom_querier::CustomQuerier;
+use super::helpers::{mock_env_at_timestamp, query_helper, query_helper_env};
+use super::tests_default::setup_test;
+
+/// Execute message of a contract implementing the cw721 receiver interface
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum MarketExecuteMsg {
+    ReceiveNft(Cw721ReceiveMsg),
+}
+
+/// Batches 1 and 2 have finished unbonding; batch 3 is pending
+fn setup_unbond_requests() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let unbond_requests = vec![
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(20000),
+        },
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_2"),
+            shares: Uint128::new(30000),
+        },
+        UnbondRequest {
+            id: 2,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(10000),
+        },
+        UnbondRequest {
+            id: 3,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(5000),
+        },
+    ];
+
+    for unbond_request in &unbond_requests {
+        state
+            .unbond_requests
+            .save(deps.as_mut().storage, (unbond_request.id, &unbond_request.user), unbond_request)
+            .unwrap();
+    }
+    state.unbond_request_count.save(deps.as_mut().storage, &4).unwrap();
+
+    let previous_batches = vec![
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(50000),
+            utoken_unclaimed: Uint128::new(51000),
+            est_unbond_end_time: 10000,
+        },
+        Batch {
+            id: 2,
+            reconciled: true,
+            total_shares: Uint128::new(10000),
+            utoken_unclaimed: Uint128::new(10300),
+            est_unbond_end_time: 20000,
+        },
+    ];
+
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
+            .unwrap();
+    }
+
+    state
+        .pending_batch
+        .save(
+            deps.as_mut().storage,
+            &PendingBatch {
+                id: 3,
+                ustake_to_burn: Uint128::new(5000),
+                est_unbond_start_time: 30000,
+            },
+        )
+        .unwrap();
+
+    deps
+}
+
+fn transfer(
+    deps: &mut OwnedDeps<MockStorage, MockApi, CustomQuerier>,
+    sender: &str,
+    recipient: &str,
+    token_id: &str,
+) -> Result<(), ContractError> {
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info(sender, &[]),
+        ExecuteMsg::TransferNft {
+            recipient: recipient.to_string(),
+            token_id: token_id.to_string(),
+        },
+    )
+    .map(|_| ())
+}
+
+fn query_tokens(deps: &OwnedDeps<MockStorage, MockApi, CustomQuerier>, owner: &str) -> Vec<String> {
+    let res: TokensResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::Tokens {
+            owner: owner.to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    res.tokens
+}
+
+#[test]
+fn transferring_unbond_requests() {
+    let mut deps = setup_unbond_requests();
+
+    let err = transfer(&mut deps, "user_1", "user_3", "1-user_1").unwrap_err();
+    assert_eq!(err, ContractError::InvalidTokenId("1-user_1".to_string()));
+
+    let err = transfer(&mut deps, "user_1", "user_3", "4/user_1").unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UnbondRequestNotFound {
+            id: 4,
+            user: "user_1".to_string(),
+        }
+    );
+
+    let err = transfer(&mut deps, "user_1", "user_3", "3/user_1").unwrap_err();
+    assert_eq!(err, ContractError::UnbondRequestNotTransferable);
+
+    let err = transfer(&mut deps, "user_2", "user_3", "1/user_1").unwrap_err();
+    assert_eq!(err, ContractError::NotTokenHolder("1/user_1".to_string()));
+
+    transfer(&mut deps, "user_1", "user_3", "1/user_1").unwrap();
+
+    let res: OwnerOfResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::OwnerOf {
+            token_id: "1/user_1".to_string(),
+            include_expired: None,
+        },
+    );
+    assert_eq!(res.owner, "user_3".to_string());
+
+    assert_eq!(query_tokens(&deps, "user_1"), vec!["2/user_1".to_string(), "3/user_1".to_string()]);
+    assert_eq!(query_tokens(&deps, "user_3"), vec!["1/user_1".to_string()]);
+
+    // The user who queued the request no longer holds it
+    let err = transfer(&mut deps, "user_1", "user_2", "1/user_1").unwrap_err();
+    assert_eq!(err, ContractError::NotTokenHolder("1/user_1".to_string()));
+
+    // Transferring the request back to the user who queued it clears the holder
+    transfer(&mut deps, "user_3", "user_1", "1/user_1").unwrap();
+
+    let holder = State::default()
+        .unbond_request_holders
+        .may_load(deps.as_ref().storage, (1, &Addr::unchecked("user_1")))
+        .unwrap();
+    assert_eq!(holder, None);
+    assert_eq!(
+        query_tokens(&deps, "user_1"),
+        vec!["1/user_1".to_string(), "2/user_1".to_string(), "3/user_1".to_string()]
+    );
+    assert_eq!(query_tokens(&deps, "user_3"), Vec::<String>::new());
+
+    // Tokens are paginated in the order of batch IDs
+    transfer(&mut deps, "user_2", "user_1", "1/user_2").unwrap();
+
+    let res: TokensResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::Tokens {
+            owner: "user_1".to_string(),
+            start_after: Some("1/user_1".to_string()),
+            limit: Some(2),
+        },
+    );
+    assert_eq!(res.tokens, vec!["1/user_2".to_string(), "2/user_1".to_string()]);
+}
+
+fn query_owner_of(
+    deps: &OwnedDeps<MockStorage, MockApi, CustomQuerier>,
+    token_id: &str,
+    include_expired: Option<bool>,
+) -> OwnerOfResponse {
+    query_helper_env(
+        deps.as_ref(),
+        QueryMsg::OwnerOf {
+            token_id: token_id.to_string(),
+            include_expired,
+        },
+        25000,
+    )
+}
+
+#[test]
+fn approving_transfers() {
+    let mut deps = setup_unbond_requests();
+
+    let mut approve = |sender: &str, spender: &str, token_id: &str, expires: Option<Expiration>| {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(25000),
+            mock_info(sender, &[]),
+            ExecuteMsg::Approve {
+                spender: spender.to_string(),
+                token_id: token_id.to_string(),
+                expires,
+            },
+        )
+        .map(|_| ())
+    };
+
+    // Only the holder can approve
+    let err = approve("user_2", "market", "1/user_1", None).unwrap_err();
+    assert_eq!(err, ContractError::NotTokenHolder("1/user_1".to_string()));
+
+    // Approvals that have already expired are rejected
+    for expires in [Expiration::AtTime(Timestamp::from_seconds(25000)), Expiration::AtHeight(1)] {
+        let err = approve("user_1", "market", "1/user_1", Some(expires)).unwrap_err();
+        assert_eq!(err, ContractError::ApprovalExpired);
+    }
+
+    approve("user_1", "market", "1/user_1", None).unwrap();
+    approve(
+        "user_1",
+        "user_3",
+        "1/user_1",
+        Some(Expiration::AtTime(Timestamp::from_seconds(30000))),
+    )
+    .unwrap();
+
+    let res = query_owner_of(&deps, "1/user_1", None);
+    assert_eq!(
+        res,
+        OwnerOfResponse {
+            owner: "user_1".to_string(),
+            approvals: vec![
+                Approval {
+                    spender: "market".to_string(),
+                    expires: Expiration::Never {},
+                },
+                Approval {
+                    spender: "user_3".to_string(),
+                    expires: Expiration::AtTime(Timestamp::from_seconds(30000)),
+                },
+            ],
+        }
+    );
+
+    // Expired approvals are only listed on request, and can't be used
+    let res: OwnerOfResponse = query_helper_env(
+        deps.as_ref(),
+        QueryMsg::OwnerOf {
+            token_id: "1/user_1".to_string(),
+            include_expired: None,
+        },
+        30001,
+    );
+    assert_eq!(res.approvals.len(), 1);
+
+    let res: OwnerOfResponse = query_helper_env(
+        deps.as_ref(),
+        QueryMsg::OwnerOf {
+            token_id: "1/user_1".to_string(),
+            include_expired: Some(true),
+        },
+        30001,
+    );
+    assert_eq!(res.approvals.len(), 2);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(30001),
+        mock_info("user_3", &[]),
+        ExecuteMsg::TransferNft {
+            recipient: "user_3".to_string(),
+            token_id: "1/user_1".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NotTokenHolder("1/user_1".to_string()));
+
+    // A revoked spender can't transfer
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::Revoke {
+            spender: "user_3".to_string(),
+            token_id: "1/user_1".to_string(),
+        },
+    )
+    .unwrap();
+
+    let err = transfer(&mut deps, "user_3", "user_3", "1/user_1").unwrap_err();
+    assert_eq!(err, ContractError::NotTokenHolder("1/user_1".to_string()));
+
+    // An approved spender can transfer, after which the approvals are cleared
+    transfer(&mut deps, "market", "user_2", "1/user_1").unwrap();
+
+    let res = query_owner_of(&deps, "1/user_1", Some(true));
+    assert_eq!(
+        res,
+        OwnerOfResponse {
+            owner: "user_2".to_string(),
+            approvals: vec![],
+        }
+    );
+
+    let err = transfer(&mut deps, "market", "user_3", "1/user_1").unwrap_err();
+    assert_eq!(err, ContractError::NotTokenHolder("1/user_1".to_string()));
+}
+
+#[test]
+fn approving_operators() {
+    let mut deps = setup_unbond_requests();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::ApproveAll {
+            operator: "market".to_string(),
+            expires: None,
+        },
+    )
+    .unwrap();
+
+    // Operators can transfer all of the holder's requests, and approve others to
+    transfer(&mut deps, "market", "user_3", "1/user_1").unwrap();
+    assert_eq!(query_tokens(&deps, "user_3"), vec!["1/user_1".to_string()]);
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("market", &[]),
+        ExecuteMsg::Approve {
+            spender: "user_4".to_string(),
+            token_id: "2/user_1".to_string(),
+            expires: None,
+        },
+    )
+    .unwrap();
+
+    transfer(&mut deps, "user_4", "user_4", "2/user_1").unwrap();
+    assert_eq!(query_tokens(&deps, "user_4"), vec!["2/user_1".to_string()]);
+
+    // Operators only act for the holder who approved them
+    let err = transfer(&mut deps, "market", "user_2", "1/user_2").unwrap_err();
+    assert_eq!(err, ContractError::NotTokenHolder("1/user_2".to_string()));
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_3", &[]),
+        ExecuteMsg::ApproveAll {
+            operator: "market".to_string(),
+            expires: None,
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_3", &[]),
+        ExecuteMsg::RevokeAll {
+            operator: "market".to_string(),
+        },
+    )
+    .unwrap();
+
+    let err = transfer(&mut deps, "market", "user_2", "1/user_1").unwrap_err();
+    assert_eq!(err, ContractError::NotTokenHolder("1/user_1".to_string()));
+}
+
+#[test]
+fn querying_token_info() {
+    let mut deps = setup_unbond_requests();
+
+    let res: NftInfoResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::NftInfo {
+            token_id: "1/user_2".to_string(),
+        },
+    );
+    assert_eq!(
+        res,
+        NftInfoResponse {
+            token_uri: None,
+            extension: UnbondRequest {
+                id: 1,
+                user: Addr::unchecked("user_2"),
+                shares: Uint128::new(30000),
+            },
+        }
+    );
+
+    let res: ContractInfoResponse = query_helper(deps.as_ref(), QueryMsg::ContractInfo {});
+    assert_eq!(
+        res,
+        ContractInfoResponse {
+            name: "Eris unbond requests".to_string(),
+            symbol: "ERIS-UNBOND".to_string(),
+        }
+    );
+
+    let res: NumTokensResponse = query_helper(deps.as_ref(), QueryMsg::NumTokens {});
+    assert_eq!(res.count, 4);
+
+    // Withdrawing burns the tokens of batches 1 and 2, and moving a request into one the recipient
+    // doesn't have yet neither mints nor burns
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            batch_ids: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    move_request(&mut deps, "user_1", 3, "user_4").unwrap();
+
+    let res: NumTokensResponse = query_helper(deps.as_ref(), QueryMsg::NumTokens {});
+    assert_eq!(res.count, 2);
+}
+
+#[test]
+fn sending_unbond_requests() {
+    let mut deps = setup_unbond_requests();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::SendNft {
+            contract: "market".to_string(),
+            token_id: "2/user_1".to_string(),
+            msg: to_binary("list").unwrap(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "market".to_string(),
+            msg: to_binary(&MarketExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                sender: "user_1".to_string(),
+                token_id: "2/user_1".to_string(),
+                msg: to_binary("list").unwrap(),
+            }))
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    assert_eq!(query_tokens(&deps, "market"), vec!["2/user_1".to_string()]);
+}
+
+#[test]
+fn withdrawing_transferred_unbond_requests() {
+    let mut deps = setup_unbond_requests();
+    let state = State::default();
+
+    transfer(&mut deps, "user_1", "user_3", "1/user_1").unwrap();
+
+    // The holder is paid batch 1: 51000 * 20000 / 50000 = 20400
+    let res: WithdrawableResponse = query_helper_env(
+        deps.as_ref(),
+        QueryMsg::Withdrawable {
+            user: "user_3".to_string(),
+        },
+        25000,
+    );
+    assert_eq!(
+        res,
+        WithdrawableResponse {
+            batch_ids: vec![1],
+            utoken: Uint128::new(20400),
+        }
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_3", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            batch_ids: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_3".to_string(),
+            amount: vec![Coin::new(20400, CONTRACT_DENOM)]
+        }))
+    );
+
+    let request =
+        state.unbond_requests.may_load(deps.as_ref().storage, (1, &Addr::unchecked("user_1")));
+    assert_eq!(request.unwrap(), None);
+    let holder = state
+        .unbond_request_holders
+        .may_load(deps.as_ref().storage, (1, &Addr::unchecked("user_1")))
+        .unwrap();
+    assert_eq!(holder, None);
+
+    // The user who queued the request is only paid batch 2
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            batch_ids: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(10300, CONTRACT_DENOM)]
+        }))
+    );
+}
+
+fn move_request(
+    deps: &mut OwnedDeps<MockStorage, MockApi, CustomQuerier>,
+    sender: &str,
+    id: u64,
+    recipient: &str,
+) -> Result<(), ContractError> {
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info(sender, &[]),
+        ExecuteMsg::TransferUnbondRequest {
+            id,
+            recipient: recipient.to_string(),
+        },
+    )
+    .map(|_| ())
+}
+
+fn query_user_requests(
+    deps: &OwnedDeps<MockStorage, MockApi, CustomQuerier>,
+    user: &str,
+) -> Vec<UnbondRequestsByUserResponseItem> {
+    query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByUser {
+            user: user.to_string(),
+            start_after: None,
+            limit: None,
+        },
+    )
+}
+
+#[test]
+fn moving_unbond_requests() {
+    let mut deps = setup_unbond_requests();
+
+    let err = move_request(&mut deps, "user_1", 1, "user_1").unwrap_err();
+    assert_eq!(err, ContractError::TransferToOwner);
+
+    let err = move_request(&mut deps, "user_3", 1, "user_1").unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UnbondRequestNotFound {
+            id: 1,
+            user: "user_3".to_string(),
+        }
+    );
+
+    // Requests held by another account as tokens can't be moved, or merged into
+    transfer(&mut deps, "user_1", "user_3", "2/user_1").unwrap();
+
+    let err = move_request(&mut deps, "user_1", 2, "user_4").unwrap_err();
+    assert_eq!(err, ContractError::UnbondRequestHeldByAnother("2/user_1".to_string()));
+
+    transfer(&mut deps, "user_2", "user_3", "1/user_2").unwrap();
+
+    let err = move_request(&mut deps, "user_1", 1, "user_2").unwrap_err();
+    assert_eq!(err, ContractError::UnbondRequestHeldByAnother("1/user_2".to_string()));
+
+    transfer(&mut deps, "user_3", "user_2", "1/user_2").unwrap();
+
+    // User 1's request in batch 1 is merged with user 2's
+    move_request(&mut deps, "user_1", 1, "user_2").unwrap();
+
+    // User 1's request in the pending batch is moved to a new address
+    move_request(&mut deps, "user_1", 3, "user_4").unwrap();
+
+    assert_eq!(
+        query_user_requests(&deps, "user_1"),
+        vec![UnbondRequestsByUserResponseItem {
+            id: 2,
+            shares: Uint128::new(10000),
+        }]
+    );
+    assert_eq!(
+        query_user_requests(&deps, "user_2"),
+        vec![UnbondRequestsByUserResponseItem {
+            id: 1,
+            shares: Uint128::new(50000),
+        }]
+    );
+    assert_eq!(
+        query_user_requests(&deps, "user_4"),
+        vec![UnbondRequestsByUserResponseItem {
+            id: 3,
+            shares: Uint128::new(5000),
+        }]
+    );
+
+    let res: Vec<UnbondRequestsByBatchResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByBatch {
+            id: 1,
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![UnbondRequestsByBatchResponseItem {
+            user: "user_2".to_string(),
+            shares: Uint128::new(50000),
+        }]
+    );
+}
@@ -0,0 +1,199 @@
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, Event, Order, Response, StdResult};
+use cw_storage_plus::Bound;
+
+use eris_staking::hub::{ConfigChange, PendingChange};
+
+use crate::error::{ContractError, ContractResult};
+use crate::execute;
+use crate::queries::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::state::State;
+
+//--------------------------------------------------------------------------------------------------
+// Proposal logics
+//--------------------------------------------------------------------------------------------------
+
+/// Apply a change proposed by the owner. If a timelock delay is set, the change is instead queued
+/// and can only be executed with `ExecuteMsg::ExecutePendingChange` once the delay has passed,
+/// giving stakers a window to exit before it lands.
+///
+/// Changes are validated both when proposed and when applied, as the state they are checked against
+/// (e.g. the validator whitelist) can change in between.
+pub fn propose_change(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    change: ConfigChange,
+) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    validate_change(deps.as_ref(), &change)?;
+
+    let delay = state.timelock_delay.may_load(deps.storage)?.unwrap_or(0);
+    if delay == 0 {
+        return apply_change(deps, env, sender, change);
+    }
+
+    let id = state.next_pending_change_id.may_load(deps.storage)?.unwrap_or(1);
+    state.next_pending_change_id.save(deps.storage, &(id + 1))?;
+
+    let executable_at = env.block.time.seconds() + delay;
+    state.pending_changes.save(
+        deps.storage,
+        id,
+        &PendingChange {
+            id,
+            change,
+            executable_at,
+        },
+    )?;
+
+    let event = Event::new("erishub/change_proposed")
+        .add_attribute("id", id.to_string())
+        .add_attribute("executable_at", executable_at.to_string());
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/propose_change"))
+}
+
+pub fn execute_pending_change(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    id: u64,
+) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let pending_change = load_pending_change(deps.as_ref(), id)?;
+    if env.block.time.seconds() < pending_change.executable_at {
+        return Err(ContractError::PendingChangeNotReady {
+            id,
+            executable_at: pending_change.executable_at,
+        });
+    }
+
+    state.pending_changes.remove(deps.storage, id);
+
+    let event = Event::new("erishub/change_executed").add_attribute("id", id.to_string());
+
+    Ok(apply_change(deps, env, sender, pending_change.change)?.add_event(event))
+}
+
+pub fn cancel_pending_change(deps: DepsMut, sender: Addr, id: u64) -> ContractResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    load_pending_change(deps.as_ref(), id)?;
+    state.pending_changes.remove(deps.storage, id);
+
+    let event = Event::new("erishub/change_cancelled").add_attribute("id", id.to_string());
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/cancel_pending_change"))
+}
+
+fn apply_change(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    change: ConfigChange,
+) -> ContractResult<Response> {
+    match change {
+        ConfigChange::UpdateConfig(msg) => execute::update_config(deps, sender, msg),
+        ConfigChange::AddValidator {
+            validator,
+            weight,
+        } => execute::add_validator(deps, sender, validator, weight),
+        ConfigChange::RemoveValidator {
+            validator,
+        } => execute::remove_validator(deps, env, sender, validator),
+        ConfigChange::UpdateValidatorWeights {
+            weights,
+        } => execute::update_validator_weights(deps, sender, weights),
+        ConfigChange::UpdateLiquidityBuffer {
+            config,
+        } => execute::update_liquidity_buffer(deps, sender, config),
+        ConfigChange::UpdateTimelockDelay {
+            delay,
+        } => update_timelock_delay(deps, delay),
+    }
+}
+
+/// Reject a change that would fail if applied right away. Whether a validator to add is active and
+/// within the maximum commission is only checked when applying, as that may change during the delay
+fn validate_change(deps: Deps, change: &ConfigChange) -> ContractResult<()> {
+    let validators = State::default().validators.load(deps.storage)?;
+
+    match change {
+        ConfigChange::UpdateConfig(msg) => execute::validate_config_update(deps.api, msg),
+        ConfigChange::AddValidator {
+            validator,
+            ..
+        } => {
+            if validators.contains(validator) {
+                return Err(ContractError::ValidatorAlreadyWhitelisted(validator.clone()));
+            }
+            Ok(())
+        },
+        ConfigChange::RemoveValidator {
+            validator,
+        } => {
+            if !validators.contains(validator) {
+                return Err(ContractError::ValidatorNotWhitelisted(validator.clone()));
+            }
+            Ok(())
+        },
+        ConfigChange::UpdateValidatorWeights {
+            weights,
+        } => execute::validate_validator_weights(deps.storage, weights),
+        ConfigChange::UpdateLiquidityBuffer {
+            config,
+        } => execute::validate_liquidity_buffer(config),
+        ConfigChange::UpdateTimelockDelay {
+            ..
+        } => Ok(()),
+    }
+}
+
+fn update_timelock_delay(deps: DepsMut, delay: u64) -> ContractResult<Response> {
+    State::default().timelock_delay.save(deps.storage, &delay)?;
+
+    let event =
+        Event::new("erishub/timelock_delay_updated").add_attribute("delay", delay.to_string());
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/update_timelock_delay"))
+}
+
+fn load_pending_change(deps: Deps, id: u64) -> ContractResult<PendingChange> {
+    State::default()
+        .pending_changes
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::PendingChangeNotFound(id))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Queries
+//--------------------------------------------------------------------------------------------------
+
+pub fn pending_changes(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<PendingChange>> {
+    let state = State::default();
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    state
+        .pending_changes
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
@@ -1,13 +1,14 @@
 use std::str::FromStr;
 
-use cosmwasm_std::{Coin, StdError, StdResult};
+use cosmwasm_std::{Coin, StdResult};
 
+use crate::error::{ContractError, ContractResult};
 use crate::helpers::parse_coin;
 
 pub struct Coins(pub Vec<Coin>);
 
 impl FromStr for Coins {
-    type Err = StdError;
+    type Err = ContractError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.is_empty() {
@@ -20,7 +21,7 @@ impl FromStr for Coins {
                 .collect::<Vec<&str>>()
                 .iter()
                 .map(|s| parse_coin(s))
-                .collect::<StdResult<Vec<Coin>>>()?,
+                .collect::<ContractResult<Vec<Coin>>>()?,
         ))
     }
 }
@@ -48,8 +49,8 @@ impl Coins {
     pub fn find(&self, denom: &str) -> Coin {
         self.0
             .iter()
-            .cloned()
             .find(|coin| coin.denom == denom)
+            .cloned()
             .unwrap_or_else(|| Coin::new(0, denom))
     }
 }
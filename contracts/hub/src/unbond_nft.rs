@@ -0,0 +1,523 @@
+use cosmwasm_std::{
+    Addr, Api, Binary, BlockInfo, Deps, DepsMut, Env, Event, Order, Response, StdResult, Storage,
+    Uint128,
+};
+use cw20::Expiration;
+
+use eris_staking::hub::{
+    Approval, Batch, ContractInfoResponse, Cw721ReceiveMsg, NftInfoResponse, NumTokensResponse,
+    OwnerOfResponse, TokensResponse, UnbondRequest,
+};
+
+use crate::constants::{UNBOND_NFT_NAME, UNBOND_NFT_SYMBOL};
+use crate::error::{ContractError, ContractResult};
+use crate::queries::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::state::State;
+
+//--------------------------------------------------------------------------------------------------
+// Token IDs
+//--------------------------------------------------------------------------------------------------
+
+/// ID of the token representing the unbond request queued by `user` in batch `id`
+pub fn token_id(id: u64, user: &Addr) -> String {
+    format!("{}/{}", id, user)
+}
+
+/// Parse a token ID into the key of the unbond request it represents
+pub(crate) fn parse_token_id(api: &dyn Api, token_id: &str) -> ContractResult<(u64, Addr)> {
+    let invalid = || ContractError::InvalidTokenId(token_id.to_string());
+
+    let (id, user) = token_id.split_once('/').ok_or_else(invalid)?;
+    let id = id.parse::<u64>().map_err(|_| invalid())?;
+    let user = api.addr_validate(user)?;
+
+    Ok((id, user))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Minting and burning
+//--------------------------------------------------------------------------------------------------
+
+/// Add shares to the unbond request queued by `user` in batch `id`, creating the request, and so
+/// minting its token, if it doesn't exist yet
+pub(crate) fn add_to_unbond_request(
+    storage: &mut dyn Storage,
+    id: u64,
+    user: &Addr,
+    shares: Uint128,
+) -> StdResult<()> {
+    let state = State::default();
+
+    let request = match state.unbond_requests.may_load(storage, (id, user))? {
+        Some(mut request) => {
+            request.shares += shares;
+            request
+        },
+        None => {
+            let count = state.unbond_request_count.may_load(storage)?.unwrap_or(0);
+            state.unbond_request_count.save(storage, &(count + 1))?;
+            UnbondRequest {
+                id,
+                user: user.clone(),
+                shares,
+            }
+        },
+    };
+
+    state.unbond_requests.save(storage, (id, user), &request)
+}
+
+/// Remove the unbond request queued by `user` in batch `id`, burning its token along with its
+/// holder, approvals and auto-claim choice
+pub(crate) fn remove_unbond_request(
+    storage: &mut dyn Storage,
+    id: u64,
+    user: &Addr,
+) -> StdResult<()> {
+    let state = State::default();
+
+    state.unbond_requests.remove(storage, (id, user))?;
+    state.unbond_request_holders.remove(storage, (id, user))?;
+    state.auto_claims.remove(storage, (id, user));
+    clear_approvals(storage, id, user)?;
+
+    let count = state.unbond_request_count.may_load(storage)?.unwrap_or(0);
+    state.unbond_request_count.save(storage, &count.saturating_sub(1))
+}
+
+fn clear_approvals(storage: &mut dyn Storage, id: u64, user: &Addr) -> StdResult<()> {
+    let state = State::default();
+
+    let spenders = state
+        .unbond_request_approvals
+        .prefix((id, user))
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for spender in spenders {
+        state.unbond_request_approvals.remove(storage, (id, user, &spender));
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Transfer logics
+//--------------------------------------------------------------------------------------------------
+
+pub fn transfer_nft(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    recipient: String,
+    token_id: String,
+) -> ContractResult<Response> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    transfer_unbond_request(deps.storage, deps.api, &env.block, &sender, &recipient, &token_id)?;
+
+    let event = Event::new("erishub/unbond_request_transferred")
+        .add_attribute("token_id", token_id)
+        .add_attribute("sender", sender)
+        .add_attribute("recipient", recipient);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/transfer_nft"))
+}
+
+pub fn send_nft(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    contract: String,
+    token_id: String,
+    msg: Binary,
+) -> ContractResult<Response> {
+    let contract = deps.api.addr_validate(&contract)?;
+
+    transfer_unbond_request(deps.storage, deps.api, &env.block, &sender, &contract, &token_id)?;
+
+    let receive_msg = Cw721ReceiveMsg {
+        sender: sender.to_string(),
+        token_id: token_id.clone(),
+        msg,
+    }
+    .into_cosmos_msg(&contract)?;
+
+    let event = Event::new("erishub/unbond_request_transferred")
+        .add_attribute("token_id", token_id)
+        .add_attribute("sender", sender)
+        .add_attribute("recipient", contract);
+
+    Ok(Response::new()
+        .add_message(receive_msg)
+        .add_event(event)
+        .add_attribute("action", "erishub/send_nft"))
+}
+
+fn transfer_unbond_request(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    block: &BlockInfo,
+    sender: &Addr,
+    recipient: &Addr,
+    token_id: &str,
+) -> ContractResult<()> {
+    let state = State::default();
+    let (id, user) = parse_token_id(api, token_id)?;
+
+    // Requests in the pending batch can still grow when the user queues more Stake token, so they
+    // are only transferable once the batch is submitted
+    let pending_batch = state.pending_batch.load(storage)?;
+    if id == pending_batch.id {
+        return Err(ContractError::UnbondRequestNotTransferable);
+    }
+
+    let holder = query_holder(storage, id, &user)?;
+    let approved = match state.unbond_request_approvals.may_load(storage, (id, &user, sender))? {
+        Some(expires) => !expires.is_expired(block),
+        None => false,
+    };
+    if holder != *sender && !approved && !is_operator(storage, block, &holder, sender)? {
+        return Err(ContractError::NotTokenHolder(token_id.to_string()));
+    }
+
+    clear_approvals(storage, id, &user)?;
+
+    if *recipient == user {
+        state.unbond_request_holders.remove(storage, (id, &user))?;
+    } else {
+        state.unbond_request_holders.save(storage, (id, &user), recipient)?;
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Approval logics
+//--------------------------------------------------------------------------------------------------
+
+pub fn approve(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    spender: String,
+    token_id: String,
+    expires: Option<Expiration>,
+) -> ContractResult<Response> {
+    let state = State::default();
+    let spender = deps.api.addr_validate(&spender)?;
+    let (id, user) = parse_token_id(deps.api, &token_id)?;
+
+    assert_can_approve(deps.storage, &env.block, &sender, id, &user, &token_id)?;
+
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::ApprovalExpired);
+    }
+
+    state.unbond_request_approvals.save(deps.storage, (id, &user, &spender), &expires)?;
+
+    let event = Event::new("erishub/unbond_request_approved")
+        .add_attribute("token_id", token_id)
+        .add_attribute("spender", spender)
+        .add_attribute("expires", expires.to_string());
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/approve"))
+}
+
+pub fn revoke(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    spender: String,
+    token_id: String,
+) -> ContractResult<Response> {
+    let state = State::default();
+    let spender = deps.api.addr_validate(&spender)?;
+    let (id, user) = parse_token_id(deps.api, &token_id)?;
+
+    assert_can_approve(deps.storage, &env.block, &sender, id, &user, &token_id)?;
+
+    state.unbond_request_approvals.remove(deps.storage, (id, &user, &spender));
+
+    let event = Event::new("erishub/unbond_request_revoked")
+        .add_attribute("token_id", token_id)
+        .add_attribute("spender", spender);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/revoke"))
+}
+
+pub fn approve_all(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    operator: String,
+    expires: Option<Expiration>,
+) -> ContractResult<Response> {
+    let state = State::default();
+    let operator = deps.api.addr_validate(&operator)?;
+
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::ApprovalExpired);
+    }
+
+    state.unbond_request_operators.save(deps.storage, (&sender, &operator), &expires)?;
+
+    let event = Event::new("erishub/operator_approved")
+        .add_attribute("owner", sender)
+        .add_attribute("operator", operator)
+        .add_attribute("expires", expires.to_string());
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/approve_all"))
+}
+
+pub fn revoke_all(deps: DepsMut, sender: Addr, operator: String) -> ContractResult<Response> {
+    let state = State::default();
+    let operator = deps.api.addr_validate(&operator)?;
+
+    state.unbond_request_operators.remove(deps.storage, (&sender, &operator));
+
+    let event = Event::new("erishub/operator_revoked")
+        .add_attribute("owner", sender)
+        .add_attribute("operator", operator);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/revoke_all"))
+}
+
+/// Only the holder of an unbond request and their operators can approve others to transfer it
+fn assert_can_approve(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    sender: &Addr,
+    id: u64,
+    user: &Addr,
+    token_id: &str,
+) -> ContractResult<()> {
+    let holder = query_holder(storage, id, user)?;
+    if holder != *sender && !is_operator(storage, block, &holder, sender)? {
+        return Err(ContractError::NotTokenHolder(token_id.to_string()));
+    }
+    Ok(())
+}
+
+fn is_operator(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    holder: &Addr,
+    operator: &Addr,
+) -> StdResult<bool> {
+    Ok(match State::default().unbond_request_operators.may_load(storage, (holder, operator))? {
+        Some(expires) => !expires.is_expired(block),
+        None => false,
+    })
+}
+
+/// The account currently holding the unbond request queued by `user` in batch `id`
+pub(crate) fn query_holder(storage: &dyn Storage, id: u64, user: &Addr) -> ContractResult<Addr> {
+    let state = State::default();
+
+    if state.unbond_requests.may_load(storage, (id, user))?.is_none() {
+        return Err(ContractError::UnbondRequestNotFound {
+            id,
+            user: user.to_string(),
+        });
+    }
+
+    Ok(state.unbond_request_holders.may_load(storage, (id, user))?.unwrap_or_else(|| user.clone()))
+}
+
+/// All unbond requests held by an account, ordered by batch ID: those it queued and did not
+/// transfer away, and those transferred to it. Requests are loaded lazily, so the caller can stop
+/// early without reading all of them
+pub(crate) fn held_unbond_requests<'a>(
+    storage: &'a dyn Storage,
+    holder: &Addr,
+) -> impl Iterator<Item = StdResult<UnbondRequest>> + 'a {
+    let state = State::default();
+
+    let mut queued = state
+        .unbond_requests
+        .idx
+        .user
+        .prefix(holder.to_string())
+        .range(storage, None, None, Order::Ascending)
+        .filter_map(move |item| {
+            let request = match item {
+                Ok((_, request)) => request,
+                Err(err) => return Some(Err(err)),
+            };
+            match State::default()
+                .unbond_request_holders
+                .may_load(storage, (request.id, &request.user))
+            {
+                Ok(None) => Some(Ok(request)),
+                Ok(Some(_)) => None,
+                Err(err) => Some(Err(err)),
+            }
+        })
+        .peekable();
+
+    let mut transferred = state
+        .unbond_request_holders
+        .idx
+        .holder
+        .prefix(holder.to_string())
+        .keys(storage, None, None, Order::Ascending)
+        .map(move |item| {
+            let (id, user) = item?;
+            State::default().unbond_requests.load(storage, (id, &user))
+        })
+        .peekable();
+
+    // Both sources are ordered by `(id, user)`; merge them while preserving the order
+    std::iter::from_fn(move || {
+        let take_queued = match (queued.peek(), transferred.peek()) {
+            (Some(Ok(a)), Some(Ok(b))) => (a.id, &a.user) < (b.id, &b.user),
+            (Some(_), None) | (Some(Err(_)), _) => true,
+            (None, Some(_)) | (_, Some(Err(_))) => false,
+            (None, None) => return None,
+        };
+        if take_queued {
+            queued.next()
+        } else {
+            transferred.next()
+        }
+    })
+}
+
+/// Unbond requests held by an account that can be withdrawn now, i.e. whose batch is reconciled
+/// and has finished unbonding. Batches in `reconciled_batches` take precedence over the stored
+/// ones, for reconciliations that are not saved. Only the batches in `batch_ids` are considered if
+/// provided, and at most `limit` requests are returned
+pub(crate) fn withdrawable_unbond_requests(
+    storage: &dyn Storage,
+    holder: &Addr,
+    current_time: u64,
+    reconciled_batches: &[Batch],
+    batch_ids: Option<&[u64]>,
+    limit: Option<u32>,
+) -> StdResult<Vec<UnbondRequest>> {
+    let state = State::default();
+    let max_id = batch_ids.map(|ids| ids.iter().max().copied().unwrap_or(0));
+
+    let mut requests = vec![];
+    for request in held_unbond_requests(storage, holder) {
+        let request = request?;
+
+        if let (Some(batch_ids), Some(max_id)) = (batch_ids, max_id) {
+            if request.id > max_id {
+                break;
+            }
+            if !batch_ids.contains(&request.id) {
+                continue;
+            }
+        }
+
+        let batch = match reconciled_batches.iter().find(|batch| batch.id == request.id) {
+            Some(batch) => Some(batch.clone()),
+            None => state.previous_batches.may_load(storage, request.id)?,
+        };
+        let withdrawable = match batch {
+            Some(batch) => batch.reconciled && batch.est_unbond_end_time < current_time,
+            None => false,
+        };
+        if withdrawable {
+            requests.push(request);
+            if limit.is_some_and(|limit| requests.len() >= limit as usize) {
+                break;
+            }
+        }
+    }
+
+    Ok(requests)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Queries
+//--------------------------------------------------------------------------------------------------
+
+pub fn owner_of(
+    deps: Deps,
+    env: Env,
+    token_id: String,
+    include_expired: Option<bool>,
+) -> StdResult<OwnerOfResponse> {
+    let state = State::default();
+    let (id, user) = parse_token_id(deps.api, &token_id)?;
+    let owner = query_holder(deps.storage, id, &user)?;
+
+    let approvals = state
+        .unbond_request_approvals
+        .prefix((id, &user))
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, expires)) => include_expired.unwrap_or(false) || !expires.is_expired(&env.block),
+            Err(_) => true,
+        })
+        .map(|item| {
+            let (spender, expires) = item?;
+            Ok(Approval {
+                spender: spender.into(),
+                expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(OwnerOfResponse {
+        owner: owner.into(),
+        approvals,
+    })
+}
+
+pub fn nft_info(deps: Deps, token_id: String) -> StdResult<NftInfoResponse> {
+    let (id, user) = parse_token_id(deps.api, &token_id)?;
+    let request = State::default()
+        .unbond_requests
+        .may_load(deps.storage, (id, &user))?
+        .ok_or_else(|| ContractError::UnbondRequestNotFound {
+            id,
+            user: user.to_string(),
+        })?;
+
+    Ok(NftInfoResponse {
+        token_uri: None,
+        extension: request,
+    })
+}
+
+pub fn contract_info() -> ContractInfoResponse {
+    ContractInfoResponse {
+        name: UNBOND_NFT_NAME.to_string(),
+        symbol: UNBOND_NFT_SYMBOL.to_string(),
+    }
+}
+
+pub fn num_tokens(deps: Deps) -> StdResult<NumTokensResponse> {
+    Ok(NumTokensResponse {
+        count: State::default().unbond_request_count.may_load(deps.storage)?.unwrap_or(0),
+    })
+}
+
+pub fn tokens(
+    deps: Deps,
+    owner: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let start = start_after.map(|token_id| parse_token_id(deps.api, &token_id)).transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let tokens = held_unbond_requests(deps.storage, &owner)
+        .filter(|request| match (request, &start) {
+            (Ok(request), Some((id, user))) => (request.id, &request.user) > (*id, user),
+            _ => true,
+        })
+        .take(limit)
+        .map(|request| request.map(|request| token_id(request.id, &request.user)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TokensResponse {
+        tokens,
+    })
+}
@@ -1,5 +1,7 @@
-use cosmwasm_std::{to_binary, Addr, Coin, CosmosMsg, Decimal, Empty, StdResult, Uint128, WasmMsg};
-use cw20::Cw20ReceiveMsg;
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Coin, CosmosMsg, Decimal, Empty, StdResult, Uint128, WasmMsg,
+};
+use cw20::{Cw20ReceiveMsg, Expiration};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -22,10 +24,14 @@ pub struct InstantiateMsg {
     /// Initial set of validators who will receive the delegations
     pub validators: Vec<String>,
 
-    /// Contract address where fees are sent
+    /// Contract address where fees are sent; receives all of them until other recipients are
+    /// configured with `UpdateConfig`
     pub protocol_fee_contract: String,
     /// Fees that are being applied during reinvest of staking rewards
     pub protocol_reward_fee: Decimal, // "1 is 100%, 0.05 is 5%"
+
+    /// Strategy used to allocate delegations between validators. Defaults to `weighted`
+    pub delegation_strategy: Option<DelegationStrategyConfig>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -42,39 +48,193 @@ pub enum ExecuteMsg {
     /// Withdraw Token that have finished unbonding in previous batches
     WithdrawUnbonded {
         receiver: Option<String>,
+        /// Only withdraw from these batches. All batches are considered if not provided
+        batch_ids: Option<Vec<u64>>,
+        /// Maximum number of batches to withdraw from
+        limit: Option<u32>,
+    },
+    /// Take back Stake token queued for unbonding in the pending batch, before it is submitted.
+    /// Cancels the sender's whole unbond request if `amount` is not provided
+    CancelUnbond {
+        amount: Option<Uint128>,
+    },
+    /// Move the sender's unbond request in batch `id` to another address, merging it with any
+    /// request the recipient has in that batch
+    TransferUnbondRequest {
+        id: u64,
+        recipient: String,
+    },
+    /// Transfer an unbond request to another account, who can then withdraw it. Unbond requests are
+    /// addressed as cw721 tokens with ID `{batch id}/{address that queued the request}`, and can
+    /// only be transferred once their batch has been submitted
+    TransferNft {
+        recipient: String,
+        token_id: String,
     },
-    /// Add a validator to the whitelist; callable by the owner
+    /// Transfer an unbond request to a contract and notify it through the cw721 receiver interface
+    SendNft {
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    },
+    /// Allow another account to transfer an unbond request held by the sender, until the
+    /// expiration if provided. Approvals are cleared when the request is transferred
+    Approve {
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+    /// Remove a previously granted approval
+    Revoke {
+        spender: String,
+        token_id: String,
+    },
+    /// Allow an operator to transfer and approve all unbond requests held by the sender, until the
+    /// expiration if provided
+    ApproveAll {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+    /// Remove a previously granted operator
+    RevokeAll {
+        operator: String,
+    },
+    /// Add a validator to the whitelist; callable by the owner. Queued as a pending change if a
+    /// timelock delay is set
     AddValidator {
         validator: String,
+        /// Target weight of the validator, relative to the other validators. Uses the default
+        /// weight if not provided
+        weight: Option<u64>,
     },
-    /// Remove a validator from the whitelist; callable by the owner
+    /// Remove a validator from the whitelist; callable by the owner. Queued as a pending change if a
+    /// timelock delay is set
     RemoveValidator {
         validator: String,
     },
+    /// Remove whitelisted validators that are no longer in the active set, e.g. jailed or
+    /// tombstoned, or whose commission exceeds the maximum, and redelegate their stake; callable by
+    /// anyone
+    CheckValidators {},
+    /// Update the target weights of whitelisted validators; callable by the owner. Queued as a
+    /// pending change if a timelock delay is set
+    UpdateValidatorWeights {
+        weights: Vec<(String, u64)>,
+    },
+    /// Update the size, fee and refill rules of the liquidity buffer; callable by the owner. Queued
+    /// as a pending change if a timelock delay is set
+    UpdateLiquidityBuffer {
+        config: LiquidityBufferConfig,
+    },
+    /// Add or replace the routes used to swap reward coins into Token during harvest, and remove
+    /// the routes of the denoms specified; callable by the owner
+    UpdateSwapRoutes {
+        add: Vec<SwapRoute>,
+        remove: Vec<String>,
+    },
+    /// Grant a role to an account; callable by the owner
+    GrantRole {
+        role: Role,
+        account: String,
+    },
+    /// Revoke a role from an account; callable by the owner
+    RevokeRole {
+        role: Role,
+        account: String,
+    },
     /// Transfer ownership to another account; will not take effect unless the new owner accepts
     TransferOwnership {
         new_owner: String,
     },
     /// Accept an ownership transfer
     AcceptOwnership {},
+    /// Pause the operations whose flags are set; callable by the owner or a guardian
+    Pause(PauseState),
+    /// Resume the operations whose flags are set; callable by the owner
+    Unpause(PauseState),
     /// Claim staking rewards, swap all for Token, and restake
     Harvest {},
-    /// Use redelegations to balance the amounts of Token delegated to validators
-    Rebalance {},
+    /// Use redelegations to move the amounts of Token delegated to validators towards their target weights
+    Rebalance {
+        /// Validators that deviate from their target by less than this amount of `utoken` are
+        /// left as they are. Moves between the others still respect the configured
+        /// `min_redelegation`
+        min_deviation: Option<Uint128>,
+        /// Maximum number of redelegations to make; the largest ones are made first
+        max_redelegations: Option<u32>,
+        /// Make moves smaller than the configured `min_redelegation`; callable by the owner or an
+        /// operator
+        force: Option<bool>,
+    },
     /// Update Token amounts in unbonding batches to reflect any slashing or rounding errors
     Reconcile {},
+    /// Send the Token of unbond requests that opted in to auto-claim and whose batch has finished
+    /// unbonding to their holders, oldest batches first; callable by anyone
+    DistributeUnbonded {
+        /// Maximum number of unbond requests to pay out
+        limit: Option<u32>,
+    },
     /// Submit the current pending batch of unbonding requests to be unbonded
-    SubmitBatch {},
+    SubmitBatch {
+        /// Submit the batch before its estimated unbonding start time; callable by the owner or an
+        /// operator
+        force: Option<bool>,
+    },
     /// Callbacks; can only be invoked by the contract itself
     Callback(CallbackMsg),
+    /// Vote the Stake token the sender has locked in the hub towards whitelisted validators, in
+    /// basis points that add up to at most 10000. An empty list withdraws the sender's votes
+    Vote {
+        votes: Vec<(String, u16)>,
+    },
+    /// Withdraw Stake token locked for voting, reducing the sender's voting power accordingly
+    UnlockVotingPower {
+        amount: Uint128,
+    },
+    /// Turn the current gauge votes into the target weights used by the `gauges` delegation
+    /// strategy; can be invoked once per epoch
+    TallyVotes {},
 
-    /// Updates the fee config,
-    UpdateConfig {
-        /// Contract address where fees are sent
-        protocol_fee_contract: Option<String>,
-        /// Fees that are being applied during reinvest of staking rewards
-        protocol_reward_fee: Option<Decimal>, // "1 is 100%, 0.05 is 5%"
+    /// Updates the fee config; callable by the owner. Queued as a pending change if a timelock
+    /// delay is set
+    UpdateConfig(UpdateConfigMsg),
+    /// Update the delay after which the changes proposed by the owner can be executed; callable by
+    /// the owner, and subject to the current delay
+    UpdateTimelockDelay {
+        delay: u64,
+    },
+    /// Apply a proposed change once its delay has passed; callable by the owner
+    ExecutePendingChange {
+        id: u64,
     },
+    /// Discard a proposed change; callable by the owner
+    CancelPendingChange {
+        id: u64,
+    },
+}
+
+/// Config values to update; those left out are unchanged. Unknown fields are rejected, so that a
+/// field that was renamed, like `protocol_fee_contract`, isn't silently ignored
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateConfigMsg {
+    /// Addresses where fees are sent, with the share of the fees each receives. The shares
+    /// must sum to 1
+    pub protocol_fee_recipients: Option<Vec<(String, Decimal)>>,
+    /// Fees that are being applied during reinvest of staking rewards
+    pub protocol_reward_fee: Option<Decimal>, // "1 is 100%, 0.05 is 5%"
+    /// Fee charged on deposits
+    pub deposit_fee: Option<Decimal>,
+    /// Whether the deposit fee is taken from the deposited Token or from the minted Stake token
+    pub deposit_fee_source: Option<DepositFeeSource>,
+    /// Fee charged on the Token withdrawn from finished unbonding batches
+    pub withdrawal_fee: Option<Decimal>,
+    /// Strategy used to allocate delegations between validators
+    pub delegation_strategy: Option<DelegationStrategyConfig>,
+    /// Maximum commission rate a whitelisted validator may charge
+    pub max_commission: Option<Decimal>,
+    /// Smallest amount of `utoken` moved by a redelegation when rebalancing
+    pub min_redelegation: Option<Uint128>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -84,14 +244,25 @@ pub enum ReceiveMsg {
     /// if `epoch_time` has elapsed since when the last unbonding queue was executed.
     QueueUnbond {
         receiver: Option<String>,
+        /// Send the Token to the receiver automatically once the batch has finished unbonding,
+        /// instead of waiting for it to be withdrawn. Leaves the current choice unchanged if not
+        /// provided. Only the receiver can opt out
+        auto_claim: Option<bool>,
+    },
+    /// Redeem the Stake token immediately against the liquidity buffer, minus the instant unbond fee
+    InstantUnbond {
+        receiver: Option<String>,
     },
+    /// Lock the Stake token in the hub, adding it to the sender's gauge voting power. Votes already
+    /// cast are updated to the new voting power
+    LockVotingPower {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum CallbackMsg {
-    /// Swap Terra stablecoins held by the contract to Token
-    // Swap {},
+    /// Swap the reward coins held by the contract that have a route configured to Token
+    Swap {},
     /// Following the swaps, stake the Token acquired to the whitelisted validators
     Reinvest {},
 
@@ -110,6 +281,30 @@ impl CallbackMsg {
     }
 }
 
+/// Message sent to a contract receiving an unbond request through `ExecuteMsg::SendNft`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw721ReceiveMsg {
+    pub sender: String,
+    pub token_id: String,
+    pub msg: Binary,
+}
+
+impl Cw721ReceiveMsg {
+    pub fn into_cosmos_msg(self, contract_addr: &Addr) -> StdResult<CosmosMsg> {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw721ReceiverExecuteMsg::ReceiveNft(self))?,
+            funds: vec![],
+        }))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Cw721ReceiverExecuteMsg {
+    ReceiveNft(Cw721ReceiveMsg),
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
@@ -146,6 +341,86 @@ pub enum QueryMsg {
         start_after: Option<u64>,
         limit: Option<u32>,
     },
+    /// The gauge votes cast by a user. Response: `UserVotes`
+    UserVotes {
+        user: String,
+    },
+    /// The gauge votes received by each whitelisted validator, and the weights of the last tally.
+    /// Response: `ValidatorVotesResponse`
+    ValidatorVotes {},
+    /// The redelegations `ExecuteMsg::Rebalance` would make with the same parameters.
+    /// Response: `Vec<RedelegationResponseItem>`
+    SimulateRebalance {
+        min_deviation: Option<Uint128>,
+        max_redelegations: Option<u32>,
+        force: Option<bool>,
+    },
+    /// Enumerate the accounts holding a role. Response: `Vec<String>`
+    RoleHolders {
+        role: Role,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Enumerate the changes proposed by the owner that are yet to be executed.
+    /// Response: `Vec<PendingChange>`
+    PendingChanges {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The routes used to swap reward coins into Token. Response: `Vec<SwapRoute>`
+    SwapRoutes {},
+    /// The amount of Token a user can withdraw right now with `ExecuteMsg::WithdrawUnbonded`.
+    /// Response: `WithdrawableResponse`
+    Withdrawable {
+        user: String,
+    },
+    /// The outcome of bonding an amount of Token at the current exchange rate, after the deposit
+    /// fee. Response: `SimulateBondResponse`
+    SimulateBond {
+        amount: Uint128,
+    },
+    /// The outcome of queuing an amount of Stake token for unbonding in the pending batch, at the
+    /// current exchange rate and after the withdrawal fee. Response: `SimulateUnbondResponse`
+    SimulateUnbond {
+        ustake: Uint128,
+    },
+    /// The hub's delegation to each whitelisted validator, with its share of the total and the
+    /// share targeted by the delegation strategy. Response: `Vec<DelegationResponseItem>`
+    Delegations {},
+    /// Enumerate the exchange rate snapshots recorded on each reinvestment and batch submission,
+    /// oldest first. Response: `Vec<ExchangeRateSnapshot>`
+    ExchangeRates {
+        /// Time of the snapshot to start after, in seconds
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The yield realized over a window ending at the latest exchange rate snapshot, annualized.
+    /// Response: `AprResponse`
+    Apr {
+        /// Length of the window, in seconds. Covers the full history if not provided
+        window: Option<u64>,
+    },
+    /// The account currently holding an unbond request, and the accounts approved to transfer it.
+    /// Expired approvals are left out unless `include_expired` is set. Response: `OwnerOfResponse`
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    /// The unbond request behind a token. Response: `NftInfoResponse`
+    NftInfo {
+        token_id: String,
+    },
+    /// Name and symbol of the unbond request tokens. Response: `ContractInfoResponse`
+    ContractInfo {},
+    /// Number of unbond requests in existence. Response: `NumTokensResponse`
+    NumTokens {},
+    /// Enumerate the unbond requests held by an account, including those transferred to it.
+    /// Response: `TokensResponse`
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -162,9 +437,23 @@ pub struct ConfigResponse {
     pub unbond_period: u64,
     /// Initial set of validators who will receive the delegations
     pub validators: Vec<String>,
+    /// Target weight of each validator, in the same order as `validators`
+    pub validator_weights: Vec<u64>,
 
     /// Information about applied fees
     pub fee_config: FeeConfig,
+    /// Strategy used to allocate delegations between validators
+    pub delegation_strategy: DelegationStrategyConfig,
+    /// Maximum commission rate a whitelisted validator may charge
+    pub max_commission: Decimal,
+    /// Smallest amount of `utoken` moved by a redelegation when rebalancing
+    pub min_redelegation: Uint128,
+    /// Size, fee and refill rules of the liquidity buffer
+    pub liquidity_buffer: LiquidityBufferConfig,
+    /// Delay after which the changes proposed by the owner can be executed, in seconds
+    pub timelock_delay: u64,
+    /// Operations currently paused
+    pub paused: PauseState,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -183,6 +472,10 @@ pub struct StateResponse {
     pub available: Uint128,
     // Total amount of utoken within the contract (bonded + unbonding + available)
     pub tvl_utoken: Uint128,
+    /// Amount of utoken held in the liquidity buffer, available for instant unbonding
+    pub buffer_utoken: Uint128,
+    /// Amount of utoken the liquidity buffer is refilled up to
+    pub buffer_target: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -197,10 +490,313 @@ pub struct PendingBatch {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct FeeConfig {
-    /// Contract address where fees are sent
-    pub protocol_fee_contract: Addr,
+    /// Addresses where fees are sent, with the share of the fees each receives, e.g. 0.5 for 50%
+    pub protocol_fee_recipients: Vec<(Addr, Decimal)>,
     /// Fees that are being applied during reinvest of staking rewards
     pub protocol_reward_fee: Decimal, // "1 is 100%, 0.05 is 5%"
+    /// Fee charged on deposits, sent to the protocol fee recipients
+    #[serde(default)]
+    pub deposit_fee: Decimal,
+    /// Whether the deposit fee is taken from the deposited Token or from the minted Stake token
+    #[serde(default)]
+    pub deposit_fee_source: DepositFeeSource,
+    /// Fee charged on the Token withdrawn from finished unbonding batches, sent to the protocol
+    /// fee recipients
+    #[serde(default)]
+    pub withdrawal_fee: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DepositFeeSource {
+    /// The fee is deducted from the deposited Token before it is staked
+    #[default]
+    Deposit,
+    /// The fee is deducted from the Stake token minted for the deposit
+    Mint,
+}
+
+/// Roles the owner can grant to other accounts, allowing them to perform part of its duties
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Runs privileged maintenance: forced rebalancing and early batch submission
+    Operator,
+    /// Pauses operations during incidents; only the owner can resume them
+    Guardian,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Operator => "operator",
+            Role::Guardian => "guardian",
+        }
+    }
+}
+
+/// A change to the contract's configuration, which the owner proposes and can execute once the
+/// timelock delay has passed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigChange {
+    UpdateConfig(UpdateConfigMsg),
+    AddValidator {
+        validator: String,
+        weight: Option<u64>,
+    },
+    RemoveValidator {
+        validator: String,
+    },
+    UpdateValidatorWeights {
+        weights: Vec<(String, u64)>,
+    },
+    UpdateLiquidityBuffer {
+        config: LiquidityBufferConfig,
+    },
+    UpdateTimelockDelay {
+        delay: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingChange {
+    /// ID of this change, used to execute or cancel it
+    pub id: u64,
+    /// The change to apply
+    pub change: ConfigChange,
+    /// Time after which the change can be executed, in seconds
+    pub executable_at: u64,
+}
+
+/// Operations that can be paused during incidents. In `ExecuteMsg::Pause` and `ExecuteMsg::Unpause`,
+/// operations whose flag is not set are left as they are
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct PauseState {
+    /// `bond` and `donate`
+    pub bond: bool,
+    /// `queue_unbond`, `instant_unbond` and `submit_batch`
+    pub unbond: bool,
+    /// `withdraw_unbonded` and `distribute_unbonded`
+    pub withdraw: bool,
+    /// `harvest`
+    pub harvest: bool,
+}
+
+/// Liquidity buffer of Token kept undelegated by the hub, against which the Stake token can be
+/// redeemed without waiting for the unbonding period
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct LiquidityBufferConfig {
+    /// Amount of utoken the buffer is refilled up to. Zero disables the buffer
+    pub target: Uint128,
+    /// Share of bonded Token kept in the buffer while it is below target
+    pub bond_share: Decimal,
+    /// Share of reinvested staking rewards kept in the buffer while it is below target
+    pub reward_share: Decimal,
+    /// Fee applied to instant unbondings, at most 5%; it stays in the buffer, to the benefit of all
+    /// stakers
+    pub instant_unbond_fee: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationStrategyConfig {
+    /// All validators receive the same share of the delegations
+    Uniform,
+    /// Validators receive delegations according to their target weights
+    Weighted,
+    /// Target weights are queried from an external contract implementing `StrategyQueryMsg`
+    External {
+        contract: String,
+    },
+    /// Target weights are the result of the last tally of the Stake token holders' gauge votes
+    Gauges,
+}
+
+/// Route through the DEX router used to swap a reward denom into Token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapRoute {
+    /// Denom of the reward coin to swap
+    pub denom: String,
+    /// Address of the router contract, implementing `RouterExecuteMsg` and `RouterQueryMsg`
+    pub router: String,
+    /// Swaps to go through, starting from `denom` and ending with Token
+    pub operations: Vec<SwapOperation>,
+    /// Maximum shortfall from the amount simulated by the router, e.g. 0.01 for 1%
+    pub max_spread: Decimal,
+    /// Minimum amount of Token to receive per unit of `denom` offered. Unlike the router's
+    /// simulation, it can't be moved within the transaction that triggers the swap, so it bounds
+    /// how much a sandwiching trader can extract
+    pub min_price: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapOperation {
+    pub offer_denom: String,
+    pub ask_denom: String,
+}
+
+/// Execute interface of the DEX routers used by `SwapRoute`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RouterExecuteMsg {
+    /// Swap the coins sent along through the operations specified, and send the result back to the
+    /// sender. Fails if it is less than `minimum_receive`
+    ExecuteSwapOperations {
+        operations: Vec<SwapOperation>,
+        minimum_receive: Option<Uint128>,
+    },
+}
+
+/// Query interface of the DEX routers used by `SwapRoute`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RouterQueryMsg {
+    /// The amount received by swapping `offer_amount` through the operations specified.
+    /// Response: `SimulateSwapOperationsResponse`
+    SimulateSwapOperations {
+        offer_amount: Uint128,
+        operations: Vec<SwapOperation>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateSwapOperationsResponse {
+    pub amount: Uint128,
+}
+
+/// Query interface of contracts used with `DelegationStrategyConfig::External`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyQueryMsg {
+    /// Target weights of the validators specified. Response: `ValidatorWeightsResponse`
+    ValidatorWeights {
+        validators: Vec<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidatorWeightsResponse {
+    /// Target weight of each validator; validators not included are assigned a zero weight
+    pub weights: Vec<(String, u64)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateBondResponse {
+    /// Amount of ustake the receiver would get, after the deposit fee
+    pub ustake_to_receive: Uint128,
+    /// Deposit fee taken from the Token deposited, if the fee is charged on the deposit
+    pub utoken_deposit_fee: Uint128,
+    /// Deposit fee taken from the Stake token minted, if the fee is charged on the mint
+    pub ustake_deposit_fee: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateUnbondResponse {
+    /// Amount of utoken the user would withdraw, after the withdrawal fee
+    pub utoken_to_receive: Uint128,
+    /// Withdrawal fee taken from the utoken unbonded
+    pub utoken_withdrawal_fee: Uint128,
+    /// ID of the batch the unbond request would be added to
+    pub batch_id: u64,
+    /// Estimated time the batch will be submitted for unbonding, in seconds
+    pub est_unbond_start_time: u64,
+    /// Estimated time the utoken can be withdrawn, in seconds
+    pub est_unbond_end_time: u64,
+}
+
+/// Exchange rate between ustake and utoken at a point in time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExchangeRateSnapshot {
+    /// Time the snapshot was taken, in seconds
+    pub time: u64,
+    /// Total amount of utoken staked, including the liquidity buffer
+    pub total_utoken: Uint128,
+    /// Total supply of the Stake token
+    pub total_ustake: Uint128,
+    /// utoken per ustake
+    pub exchange_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AprResponse {
+    /// Annualized growth of the exchange rate between `start` and `end`; zero if it decreased, e.g.
+    /// after slashing. `None` if the window does not contain two snapshots
+    pub apr: Option<Decimal>,
+    /// Oldest snapshot in the window
+    pub start: Option<ExchangeRateSnapshot>,
+    /// Latest snapshot
+    pub end: Option<ExchangeRateSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawableResponse {
+    /// IDs of the batches that can be withdrawn from
+    pub batch_ids: Vec<u64>,
+    /// Amount of utoken withdrawable, after the withdrawal fee
+    pub utoken: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Approval {
+    /// Account that can transfer the unbond request
+    pub spender: String,
+    /// When the approval expires
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnerOfResponse {
+    /// Account currently holding the unbond request
+    pub owner: String,
+    /// Accounts approved to transfer the unbond request, besides the holder's operators
+    pub approvals: Vec<Approval>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftInfoResponse {
+    /// Unbond requests have no off-chain metadata, so this is always empty
+    pub token_uri: Option<String>,
+    /// The unbond request represented by the token
+    pub extension: UnbondRequest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractInfoResponse {
+    pub name: String,
+    pub symbol: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NumTokensResponse {
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokensResponse {
+    /// IDs of the unbond requests held by the account
+    pub tokens: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserVotes {
+    /// The user's address
+    pub user: Addr,
+    /// Stake token locked by the user in the hub, which is their voting power
+    pub power: Uint128,
+    /// Share of the voting power given to each validator, in basis points
+    pub votes: Vec<(String, u16)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidatorVotesResponse {
+    /// Time of the last tally, in seconds
+    pub last_tally_time: u64,
+    /// Votes currently received by each whitelisted validator
+    pub votes: Vec<(String, Uint128)>,
+    /// Target weights resulting from the last tally, in basis points
+    pub tallied_weights: Vec<(String, u64)>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -227,6 +823,30 @@ pub struct UnbondRequest {
     pub shares: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegationResponseItem {
+    /// Validator the `utoken` is delegated to
+    pub validator: String,
+    /// Amount of `utoken` delegated
+    pub amount: Uint128,
+    /// Share of the total amount delegated by the hub
+    pub share: Decimal,
+    /// Share of the total amount delegated targeted by the delegation strategy
+    pub target_share: Decimal,
+    /// Staking rewards accumulated and not yet withdrawn
+    pub pending_rewards: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RedelegationResponseItem {
+    /// Validator the `utoken` is redelegated from
+    pub src: String,
+    /// Validator the `utoken` is redelegated to
+    pub dst: String,
+    /// Amount of `utoken` redelegated
+    pub amount: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct UnbondRequestsByBatchResponseItem {
     /// The user's address